@@ -0,0 +1,2 @@
+ALTER TABLE urls
+ADD COLUMN is_alias BOOLEAN NOT NULL DEFAULT FALSE;
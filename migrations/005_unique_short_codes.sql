@@ -0,0 +1,15 @@
+-- older rows could share a code (same url hashed for different users);
+-- keep the oldest live row and move the rest to a suffixed code
+UPDATE urls u
+SET short_url = u.short_url || '-' || substr(md5(u.id::text), 1, 4)
+WHERE u.deleted = false
+  AND EXISTS (
+    SELECT 1 FROM urls o
+    WHERE o.short_url = u.short_url
+      AND o.deleted = false
+      AND (o.created_at, o.id) < (u.created_at, u.id)
+  );
+
+CREATE UNIQUE INDEX idx_urls_short_url_live ON urls (short_url) WHERE deleted = false;
+
+CREATE SEQUENCE short_code_seq;
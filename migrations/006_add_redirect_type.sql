@@ -0,0 +1,3 @@
+-- NULL falls back to the deployment default (DEFAULT_REDIRECT_TYPE)
+ALTER TABLE urls
+ADD COLUMN redirect_type SMALLINT;
@@ -0,0 +1,3 @@
+ALTER TABLE urls
+ADD COLUMN max_clicks INTEGER,
+ADD COLUMN click_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,15 @@
+CREATE TABLE utm_presets
+(
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    params JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+
+CREATE INDEX idx_utm_presets_user_id ON utm_presets (user_id);
+
+ALTER TABLE urls
+ADD COLUMN utm_params JSONB,
+ADD COLUMN utm_preset_id UUID REFERENCES utm_presets (id) ON DELETE SET NULL;
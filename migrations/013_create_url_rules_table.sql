@@ -0,0 +1,11 @@
+CREATE TABLE url_rules
+(
+    id UUID PRIMARY KEY,
+    url_id UUID NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    conditions JSONB NOT NULL,
+    target_url TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_url_rules_url_id ON url_rules (url_id, position);
@@ -0,0 +1,15 @@
+CREATE TABLE url_variants
+(
+    id UUID PRIMARY KEY,
+    url_id UUID NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    target_url TEXT NOT NULL,
+    weight INTEGER NOT NULL,
+    visit_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_url_variants_url_id ON url_variants (url_id, position);
+
+ALTER TABLE urls
+ADD COLUMN sticky_variants BOOLEAN NOT NULL DEFAULT FALSE;
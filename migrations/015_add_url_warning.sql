@@ -0,0 +1,3 @@
+ALTER TABLE urls
+ADD COLUMN warn BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN warn_reason TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE users
+ADD COLUMN dead_link_url TEXT;
@@ -0,0 +1,13 @@
+CREATE TABLE clicks
+(
+    id UUID PRIMARY KEY,
+    url_id UUID NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    clicked_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    referrer TEXT,
+    user_agent TEXT,
+    ip_hash TEXT,
+    country TEXT,
+    variant_id UUID
+);
+
+CREATE INDEX idx_clicks_url_id ON clicks (url_id, clicked_at);
@@ -0,0 +1,37 @@
+ALTER TABLE clicks
+ADD COLUMN browser TEXT,
+ADD COLUMN os TEXT,
+ADD COLUMN device TEXT;
+
+-- Same patterns as src/common/user_agents.regexes, `\b` is `\y` in Postgres.
+UPDATE clicks SET
+    os = CASE
+        WHEN user_agent ~ 'iPhone|iPad|iPod|CPU (?:iPhone )?OS [0-9]' THEN 'ios'
+        WHEN user_agent ~ 'Android' THEN 'android'
+        WHEN user_agent ~ 'CrOS' THEN 'chromeos'
+        WHEN user_agent ~ 'Windows' THEN 'windows'
+        WHEN user_agent ~ 'Mac OS X|Macintosh' THEN 'macos'
+        WHEN user_agent ~ 'Linux|X11|Ubuntu|Fedora' THEN 'linux'
+        ELSE 'other'
+    END,
+    browser = CASE
+        WHEN user_agent ~ 'Edg(?:e|A|iOS)?/' THEN 'edge'
+        WHEN user_agent ~ 'OPR/|OPiOS/|Opera' THEN 'opera'
+        WHEN user_agent ~ 'SamsungBrowser/' THEN 'samsung'
+        WHEN user_agent ~ 'YaBrowser/' THEN 'yandex'
+        WHEN user_agent ~ 'UCBrowser/|UCWEB' THEN 'uc'
+        WHEN user_agent ~ 'Vivaldi/' THEN 'vivaldi'
+        WHEN user_agent ~ 'Firefox/|FxiOS/' THEN 'firefox'
+        WHEN user_agent ~ 'MSIE |Trident/' THEN 'ie'
+        WHEN user_agent ~ 'Chrome/|CriOS/' THEN 'chrome'
+        WHEN user_agent ~ 'Version/[0-9.]+.*Safari/' THEN 'safari'
+        ELSE 'other'
+    END,
+    device = CASE
+        WHEN user_agent ~* 'bot\y|crawl|spider|slurp|facebookexternalhit|preview|curl/|wget/|python-requests|headless' THEN 'bot'
+        WHEN user_agent ~ 'iPad|Tablet|Kindle|Silk/' THEN 'tablet'
+        WHEN user_agent ~ 'Mobi|iPhone|iPod|Android.*Mobile|Windows Phone' THEN 'mobile'
+        WHEN user_agent ~ 'Android' THEN 'tablet'
+        ELSE 'desktop'
+    END
+WHERE user_agent IS NOT NULL;
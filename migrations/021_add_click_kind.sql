@@ -0,0 +1,7 @@
+ALTER TABLE clicks
+ADD COLUMN kind TEXT NOT NULL DEFAULT 'human';
+
+-- The bot pattern of src/common/user_agents.regexes, `\b` is `\y` in Postgres.
+UPDATE clicks SET kind = 'bot'
+WHERE user_agent IS NULL
+    OR user_agent ~* 'bot\y|crawl|spider|slurp|facebookexternalhit|preview|curl/|wget/|python-requests|headless';
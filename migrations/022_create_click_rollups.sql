@@ -0,0 +1,39 @@
+-- Per link counts in 15 minute buckets (UTC), so that ranges in any time
+-- zone, including those offset by part of an hour, fall on bucket edges.
+-- `dimension` is `total` for the click count, `visitor` for one row per
+-- distinct visitor hash, or the attribute the clicks are broken down by. A
+-- missing value is stored as ''.
+--
+-- The visitor rows keep unique visitor counts exact over any range, at the
+-- cost of up to one row per click for links whose clicks mostly come from
+-- different visitors.
+CREATE TABLE click_rollups
+(
+    url_id UUID NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    bucket TIMESTAMP NOT NULL,
+    kind TEXT NOT NULL,
+    dimension TEXT NOT NULL,
+    value TEXT NOT NULL,
+    clicks BIGINT NOT NULL,
+    PRIMARY KEY (url_id, bucket, kind, dimension, value)
+);
+
+-- The same counts per day (UTC), read for the days a range covers whole.
+CREATE TABLE click_rollups_daily
+(
+    url_id UUID NOT NULL REFERENCES urls (id) ON DELETE CASCADE,
+    day TIMESTAMP NOT NULL,
+    kind TEXT NOT NULL,
+    dimension TEXT NOT NULL,
+    value TEXT NOT NULL,
+    clicks BIGINT NOT NULL,
+    PRIMARY KEY (url_id, day, kind, dimension, value)
+);
+
+-- Clicks are counted from the rollups once `rolled_up` is set, and from the
+-- clicks table until then.
+ALTER TABLE clicks
+ADD COLUMN rolled_up BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_clicks_not_rolled_up ON clicks (clicked_at) WHERE NOT rolled_up;
+CREATE INDEX idx_clicks_clicked_at ON clicks (clicked_at);
@@ -6,9 +6,11 @@ use crate::{
     app_state::AppState,
     config::config::Config,
     handlers::{
-        health::{HealthApiDoc, health_route},
-        urls::{UrlApiDoc, url_routes},
-        users::{UserApiDoc, user_routes},
+        analytics::{AnalyticsApiDoc, analytics_routes},
+        health::{HealthApiDoc, health_admin_routes, health_route},
+        robots::robots_routes,
+        urls::{UrlApiDoc, redirect_routes, url_routes},
+        users::{UserApiDoc, user_routes, user_settings_routes},
     },
     middleware::jwt::jwt_auth,
 };
@@ -19,10 +21,15 @@ pub fn create_router(config: &Config, state: AppState) -> Router {
     let public_routes = Router::new()
         .nest("/users", user_routes())
         .nest("/health", health_route())
+        .merge(robots_routes())
+        .merge(redirect_routes())
         .layer(cors.clone());
 
     let private_routes = Router::new()
         .merge(url_routes())
+        .merge(user_settings_routes())
+        .merge(analytics_routes())
+        .merge(health_admin_routes())
         .route_layer(middleware::from_fn(jwt_auth))
         .layer(cors);
 
@@ -44,4 +51,8 @@ pub fn create_swagger_ui() -> SwaggerUi {
         .url("/api-docs/urls/openapi.json", UrlApiDoc::openapi())
         .url("/api-docs/users/openapi.json", UserApiDoc::openapi())
         .url("/api-docs/health/openapi.json", HealthApiDoc::openapi())
+        .url(
+            "/api-docs/analytics/openapi.json",
+            AnalyticsApiDoc::openapi(),
+        )
 }
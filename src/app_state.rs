@@ -1,31 +1,92 @@
-use std::sync::Arc;
+use std::{fs, sync::Arc, time::Duration};
+
+use ipnet::IpNet;
 
 use sqlx::{Pool, Postgres};
+use tracing::warn;
 
 use crate::{
+    common::{client_ip, constants},
     config::config::Config,
-    domains::{auth::auth::Auth, urls::service::UrlService, users::service::UserService},
-    infra::repositories::{urls::repository::UrlRepo, users::repository::UsersRepo},
+    domains::{
+        analytics::service::AnalyticsService, auth::auth::Auth, urls::service::UrlService,
+        users::service::UserService,
+    },
+    infra::{
+        click_buffer::ClickBuffer,
+        click_rollups::ClickRollups,
+        geoip::GeoIp,
+        repositories::{
+            clicks::repository::ClickRepo, urls::repository::UrlRepo, users::repository::UsersRepo,
+        },
+    },
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub url_service: UrlService,
     pub user_service: UserService,
+    pub analytics_service: AnalyticsService,
+    pub trusted_proxies: Arc<Vec<IpNet>>,
+    pub robots_txt: Arc<String>,
+    pub clicks: ClickBuffer,
 }
 
 impl AppState {
     pub fn new(config: &Config, pool: Pool<Postgres>) -> Self {
         let url_repo = UrlRepo::new(pool.clone());
+        let click_repo = Arc::new(ClickRepo::new(pool.clone()));
+        let clicks = ClickBuffer::start(
+            click_repo.clone(),
+            config.click_buffer_size,
+            config.click_batch_size,
+            Duration::from_millis(config.click_flush_interval_ms),
+        );
+        Arc::new(ClickRollups::new(
+            click_repo.clone(),
+            config.click_retention_days,
+        ))
+        .schedule(Duration::from_secs(config.click_rollup_interval_secs));
         let users_repo = Arc::new(UsersRepo::new(pool.clone()));
 
-        let url_service = UrlService::new(url_repo, Arc::clone(&users_repo), config);
+        let geoip = Arc::new(GeoIp::new(config.geoip_db_path.clone()));
+        Arc::clone(&geoip).watch(Duration::from_secs(config.geoip_reload_secs));
+
+        let url_service = UrlService::new(
+            url_repo,
+            clicks.clone(),
+            Arc::clone(&users_repo),
+            geoip,
+            config,
+        );
+        let analytics_service =
+            AnalyticsService::new(click_repo, UrlRepo::new(pool.clone()), config);
         let auth_repo = Auth::new(config);
         let user_service = UserService::new(Arc::clone(&users_repo), auth_repo);
 
         Self {
             url_service,
             user_service,
+            analytics_service,
+            trusted_proxies: Arc::new(
+                client_ip::parse_networks(&config.trusted_proxies)
+                    .expect("TRUSTED_PROXIES is checked by Config::validate"),
+            ),
+            robots_txt: Arc::new(Self::load_robots_txt(config)),
+            clicks,
+        }
+    }
+
+    fn load_robots_txt(config: &Config) -> String {
+        let Some(path) = &config.robots_txt_path else {
+            return constants::DEFAULT_ROBOTS_TXT.to_string();
+        };
+        match fs::read_to_string(path) {
+            Ok(robots_txt) => robots_txt,
+            Err(e) => {
+                warn!("robots.txt {} not readable, using the default: {}", path, e);
+                constants::DEFAULT_ROBOTS_TXT.to_string()
+            }
         }
     }
 }
@@ -1 +1,57 @@
-pub const URL_REGEX: &str = r"^((ftp|http|https):\/\/)?(www.)?(?!.*(ftp|http|https|www.))[a-zA-Z0-9_-]+(\.[a-zA-Z]+)+((\/)[\w#]+)*(\/\w+\?[a-zA-Z0-9_]+=\w+(&[a-zA-Z0-9_]+=\w+)*)?\/?$";
\ No newline at end of file
+pub const URL_REGEX: &str = r"^((ftp|http|https):\/\/)?(www.)?(?!.*(ftp|http|https|www.))[a-zA-Z0-9_-]+(\.[a-zA-Z]+)+((\/)[\w#]+)*(\/\w+\?[a-zA-Z0-9_]+=\w+(&[a-zA-Z0-9_]+=\w+)*)?\/?$";
+
+pub const ALIAS_REGEX: &str = r"^[a-zA-Z0-9_-]{3,32}$";
+
+// top level paths that a custom alias would shadow
+pub const RESERVED_CODES: &[&str] = &[
+    "urls",
+    "users",
+    "admin",
+    "health",
+    "swagger-ui",
+    "api-docs",
+    "analytics",
+];
+
+pub const REDIRECT_TYPES: [u16; 4] = [301, 302, 307, 308];
+
+pub const SHORT_CODE_STRATEGIES: &[&str] = &["random", "counter", "hash"];
+
+pub const INACTIVE_LINK_RESPONSES: &[&str] = &["not_found", "placeholder"];
+
+pub const PERMANENT_CACHE_CONTROL: &str = "public, max-age=86400";
+pub const TEMPORARY_CACHE_CONTROL: &str = "private, no-cache, no-store, must-revalidate";
+
+pub const VARIANT_COOKIE: &str = "variant";
+pub const VARIANT_COOKIE_MAX_AGE: u64 = 60 * 60 * 24 * 30;
+
+pub const WEB_SCHEMES: &[&str] = &["http", "https"];
+
+// schemes a deep link may never use, whatever the deployment allows
+pub const BLOCKED_DEEP_LINK_SCHEMES: &[&str] = &[
+    "http",
+    "https",
+    "javascript",
+    "vbscript",
+    "data",
+    "file",
+    "blob",
+    "about",
+    "filesystem",
+];
+
+// headers browsers send with prefetch and link preview requests
+pub const PURPOSE_HEADERS: [&str; 3] = ["sec-purpose", "purpose", "x-purpose"];
+
+pub const X_ROBOTS_TAG: &str = "x-robots-tag";
+pub const ROBOTS_TAG_ALLOW: &str = "all";
+pub const ROBOTS_TAG_DENY: &str = "noindex, nofollow";
+
+pub const DEFAULT_ROBOTS_TXT: &str = "User-agent: *
+Disallow: /urls/
+Disallow: /users/
+Disallow: /admin/
+Disallow: /analytics/
+Disallow: /swagger-ui/
+Disallow: /api-docs/
+";
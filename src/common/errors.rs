@@ -24,12 +24,24 @@ pub enum AppError {
     #[error("Not found: {0}")]
     NotFound(String), // Used for not found errors
 
+    #[error("Gone: {0}")]
+    Gone(String), // Used for links that existed but can no longer be used
+
     #[error("Internal server error")]
     InternalError,
 
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String), // Used when a resource is already taken
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
     #[error("Invalid token")]
     InvalidToken,
     #[error("Token creation error")]
@@ -42,6 +54,12 @@ impl From<argon2::password_hash::Error> for AppError {
     }
 }
 
+pub fn is_unique_violation(err: &SqlxError) -> bool {
+    err.as_database_error()
+        .map(|e| e.is_unique_violation())
+        .unwrap_or(false)
+}
+
 // impl From<SqlxError> for AppError {
 //     fn from(err: SqlxError) -> Self {
 //         match err.as_database_error() {
@@ -78,7 +96,11 @@ impl IntoResponse for AppError {
             }
             AppError::PasswordHashingError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             AppError::InvalidToken => StatusCode::UNAUTHORIZED,
             AppError::TokenCreation => StatusCode::INTERNAL_SERVER_ERROR,
         };
@@ -1,3 +1,6 @@
+pub mod client_ip;
+pub mod constants;
 pub mod errors;
+pub mod rate_limit;
 pub mod response;
-pub mod constants;
\ No newline at end of file
+pub mod user_agent;
@@ -0,0 +1,45 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Counts failed attempts per key inside a fixed window.
+pub struct AttemptLimiter<K> {
+    max_attempts: u32,
+    window: Duration,
+    attempts: Mutex<HashMap<K, (u32, Instant)>>,
+}
+
+impl<K: Hash + Eq + Clone> AttemptLimiter<K> {
+    pub fn new(max_attempts: u32, window: Duration) -> Self {
+        AttemptLimiter {
+            max_attempts,
+            window,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_locked(&self, key: &K) -> bool {
+        let attempts = self.attempts.lock().unwrap();
+        match attempts.get(key) {
+            Some((count, started)) => {
+                started.elapsed() < self.window && *count >= self.max_attempts
+            }
+            None => false,
+        }
+    }
+
+    pub fn record_failure(&self, key: &K) {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.retain(|_, (_, started)| started.elapsed() < self.window);
+
+        let entry = attempts.entry(key.clone()).or_insert((0, Instant::now()));
+        entry.0 += 1;
+    }
+
+    pub fn reset(&self, key: &K) {
+        self.attempts.lock().unwrap().remove(key);
+    }
+}
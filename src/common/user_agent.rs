@@ -0,0 +1,70 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+pub const UNKNOWN: &str = "other";
+pub const DEFAULT_DEVICE: &str = "desktop";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAgent {
+    pub browser: String,
+    pub os: String,
+    pub device: String,
+}
+
+struct Patterns {
+    os: Vec<(String, Regex)>,
+    browser: Vec<(String, Regex)>,
+    device: Vec<(String, Regex)>,
+}
+
+static PATTERNS: LazyLock<Patterns> = LazyLock::new(|| load(include_str!("user_agents.regexes")));
+
+/// Reads the bundled pattern file. It ships inside the binary, so a broken
+/// pattern is a bug and fails loudly on first use.
+fn load(source: &str) -> Patterns {
+    let mut patterns = Patterns {
+        os: Vec::new(),
+        browser: Vec::new(),
+        device: Vec::new(),
+    };
+    let mut section = None;
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name);
+            continue;
+        }
+
+        let (family, pattern) = line
+            .split_once(char::is_whitespace)
+            .unwrap_or_else(|| panic!("User-Agent pattern without a family: {}", line));
+        let regex = Regex::new(pattern.trim())
+            .unwrap_or_else(|e| panic!("Invalid User-Agent pattern for {}: {}", family, e));
+        let list = match section {
+            Some("os") => &mut patterns.os,
+            Some("browser") => &mut patterns.browser,
+            Some("device") => &mut patterns.device,
+            _ => panic!("User-Agent pattern outside a known section: {}", line),
+        };
+        list.push((family.to_string(), regex));
+    }
+    patterns
+}
+
+fn find(patterns: &[(String, Regex)], ua: &str) -> Option<String> {
+    patterns
+        .iter()
+        .find(|(_, rgx)| rgx.is_match(ua))
+        .map(|(family, _)| family.clone())
+}
+
+pub fn parse(ua: &str) -> UserAgent {
+    UserAgent {
+        browser: find(&PATTERNS.browser, ua).unwrap_or_else(|| UNKNOWN.to_string()),
+        os: find(&PATTERNS.os, ua).unwrap_or_else(|| UNKNOWN.to_string()),
+        device: find(&PATTERNS.device, ua).unwrap_or_else(|| DEFAULT_DEVICE.to_string()),
+    }
+}
@@ -1,6 +1,18 @@
 use std::env;
 
 use serde::Deserialize;
+use thiserror::Error;
+
+use crate::common::{client_ip, constants};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Missing(#[from] env::VarError),
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
 
 #[derive(Default, Clone, Debug, Deserialize)]
 pub struct Config {
@@ -13,13 +25,47 @@ pub struct Config {
 
     pub service_host: String,
     pub service_port: String,
+
+    pub short_code_strategy: String,
+    pub short_code_length: usize,
+    pub short_code_salt: String,
+
+    pub default_redirect_type: u16,
+
+    pub link_password_max_attempts: u32,
+    pub link_password_lockout_secs: u64,
+
+    pub inactive_link_response: String,
+
+    pub geoip_db_path: Option<String>,
+    pub geoip_reload_secs: u64,
+    pub trusted_proxies: String,
+
+    pub admin_users: String,
+
+    pub deep_link_schemes: String,
+
+    pub dead_link_fallback_url: Option<String>,
+    pub dead_link_page: Option<String>,
+
+    pub allow_indexing: bool,
+    pub robots_txt_path: Option<String>,
+
+    pub click_ip_salt: String,
+    pub click_buffer_size: usize,
+    pub click_batch_size: usize,
+    pub click_flush_interval_ms: u64,
+    pub click_rollup_interval_secs: u64,
+    pub click_retention_days: u32,
+
+    pub bot_ips: String,
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self, env::VarError> {
+    pub fn from_env() -> Result<Self, ConfigError> {
         dotenv::dotenv().ok();
 
-        Ok(Self {
+        let config = Self {
             database_url: env::var("DATABASE_URL")?,
             database_charset: env::var("DATABASE_CHARSET")
                 .unwrap_or_else(|_| "utf8mb4".to_string()),
@@ -34,6 +80,204 @@ impl Config {
 
             service_host: env::var("SERVICE_HOST")?,
             service_port: env::var("SERVICE_PORT")?,
-        })
+
+            short_code_strategy: env::var("SHORT_CODE_STRATEGY")
+                .unwrap_or_else(|_| "random".to_string()),
+            short_code_length: env::var("SHORT_CODE_LENGTH")
+                .map(|s| s.parse::<usize>().unwrap_or(8))
+                .unwrap_or(8),
+            short_code_salt: env::var("SHORT_CODE_SALT").unwrap_or_default(),
+
+            default_redirect_type: match env::var("DEFAULT_REDIRECT_TYPE") {
+                Ok(s) => s.parse::<u16>().map_err(|_| {
+                    ConfigError::Invalid(format!(
+                        "DEFAULT_REDIRECT_TYPE must be a number, got {:?}",
+                        s
+                    ))
+                })?,
+                Err(_) => 302,
+            },
+
+            link_password_max_attempts: env::var("LINK_PASSWORD_MAX_ATTEMPTS")
+                .map(|s| s.parse::<u32>().unwrap_or(5))
+                .unwrap_or(5),
+            link_password_lockout_secs: env::var("LINK_PASSWORD_LOCKOUT_SECS")
+                .map(|s| s.parse::<u64>().unwrap_or(900))
+                .unwrap_or(900),
+
+            inactive_link_response: env::var("INACTIVE_LINK_RESPONSE")
+                .unwrap_or_else(|_| "not_found".to_string()),
+
+            geoip_db_path: env::var("GEOIP_DB_PATH").ok(),
+            geoip_reload_secs: env::var("GEOIP_RELOAD_SECS")
+                .map(|s| s.parse::<u64>().unwrap_or(60))
+                .unwrap_or(60),
+            trusted_proxies: env::var("TRUSTED_PROXIES").unwrap_or_default(),
+
+            admin_users: env::var("ADMIN_USERS").unwrap_or_default(),
+
+            deep_link_schemes: env::var("DEEP_LINK_SCHEMES").unwrap_or_default(),
+
+            dead_link_fallback_url: env::var("DEAD_LINK_FALLBACK_URL").ok(),
+            dead_link_page: env::var("DEAD_LINK_PAGE").ok(),
+
+            allow_indexing: env::var("ALLOW_INDEXING")
+                .map(|s| s.parse::<bool>().unwrap_or(false))
+                .unwrap_or(false),
+            robots_txt_path: env::var("ROBOTS_TXT_PATH").ok(),
+
+            click_ip_salt: env::var("CLICK_IP_SALT")?,
+            click_buffer_size: env::var("CLICK_BUFFER_SIZE")
+                .map(|s| s.parse::<usize>().unwrap_or(10_000))
+                .unwrap_or(10_000),
+            click_batch_size: env::var("CLICK_BATCH_SIZE")
+                .map(|s| s.parse::<usize>().unwrap_or(500))
+                .unwrap_or(500),
+            click_flush_interval_ms: env::var("CLICK_FLUSH_INTERVAL_MS")
+                .map(|s| s.parse::<u64>().unwrap_or(1000))
+                .unwrap_or(1000),
+            click_rollup_interval_secs: env::var("CLICK_ROLLUP_INTERVAL_SECS")
+                .map(|s| s.parse::<u64>().unwrap_or(600))
+                .unwrap_or(600),
+            click_retention_days: env::var("CLICK_RETENTION_DAYS")
+                .map(|s| s.parse::<u32>().unwrap_or(90))
+                .unwrap_or(90),
+
+            bot_ips: env::var("BOT_IPS").unwrap_or_default(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Rejects settings that would otherwise quietly fall back to something
+    /// the operator didn't ask for.
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !constants::SHORT_CODE_STRATEGIES.contains(&self.short_code_strategy.as_str()) {
+            return Err(ConfigError::Invalid(format!(
+                "SHORT_CODE_STRATEGY must be one of {:?}, got {:?}",
+                constants::SHORT_CODE_STRATEGIES,
+                self.short_code_strategy
+            )));
+        }
+        if self.short_code_length == 0 {
+            return Err(ConfigError::Invalid(
+                "SHORT_CODE_LENGTH must be at least 1".to_string(),
+            ));
+        }
+        if !constants::REDIRECT_TYPES.contains(&self.default_redirect_type) {
+            return Err(ConfigError::Invalid(format!(
+                "DEFAULT_REDIRECT_TYPE must be one of {:?}, got {}",
+                constants::REDIRECT_TYPES,
+                self.default_redirect_type
+            )));
+        }
+        if !constants::INACTIVE_LINK_RESPONSES.contains(&self.inactive_link_response.as_str()) {
+            return Err(ConfigError::Invalid(format!(
+                "INACTIVE_LINK_RESPONSE must be one of {:?}, got {:?}",
+                constants::INACTIVE_LINK_RESPONSES,
+                self.inactive_link_response
+            )));
+        }
+        client_ip::parse_networks(&self.trusted_proxies)
+            .map_err(|e| ConfigError::Invalid(format!("TRUSTED_PROXIES has an {}", e)))?;
+        client_ip::parse_networks(&self.bot_ips)
+            .map_err(|e| ConfigError::Invalid(format!("BOT_IPS has an {}", e)))?;
+        if self.click_ip_salt.is_empty() {
+            return Err(ConfigError::Invalid(
+                "CLICK_IP_SALT must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            short_code_strategy: "random".to_string(),
+            short_code_length: 8,
+            default_redirect_type: 302,
+            inactive_link_response: "not_found".to_string(),
+            click_ip_salt: "salt".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn accepts_the_defaults() {
+        assert!(config().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_an_unknown_short_code_strategy() {
+        let config = Config {
+            short_code_strategy: "counterr".to_string(),
+            ..config()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_empty_short_codes() {
+        let config = Config {
+            short_code_length: 0,
+            ..config()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_a_redirect_type_that_isnt_a_redirect() {
+        for default_redirect_type in [200, 404] {
+            let config = Config {
+                default_redirect_type,
+                ..config()
+            };
+            assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_an_unknown_inactive_link_response() {
+        let config = Config {
+            inactive_link_response: "placholder".to_string(),
+            ..config()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_trusted_proxies() {
+        let invalid = Config {
+            trusted_proxies: "10.0.0.0/8, 10.0.0.300".to_string(),
+            ..config()
+        };
+        assert!(matches!(invalid.validate(), Err(ConfigError::Invalid(_))));
+        let valid = Config {
+            trusted_proxies: "10.0.0.0/8, 192.168.1.1, ::1".to_string(),
+            ..config()
+        };
+        assert!(valid.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_bot_ips() {
+        let config = Config {
+            bot_ips: "66.249.64.0/19, crawler.example.com".to_string(),
+            ..config()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_an_empty_click_ip_salt() {
+        let config = Config {
+            click_ip_salt: String::new(),
+            ..config()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
     }
 }
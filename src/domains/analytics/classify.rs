@@ -0,0 +1,130 @@
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+use crate::common::user_agent::UserAgent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickKind {
+    Human,
+    /// Crawlers, link unfurlers, uptime monitors and scanners
+    Bot,
+    /// Fetched ahead of time by the browser, the visitor may never see the page
+    Prefetch,
+}
+
+impl ClickKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClickKind::Human => "human",
+            ClickKind::Bot => "bot",
+            ClickKind::Prefetch => "prefetch",
+        }
+    }
+}
+
+/// Tells visitors apart from automated fetches. `purpose` is the value of the
+/// `Sec-Purpose`, `Purpose` or `X-Purpose` header, whichever was sent.
+pub fn classify(
+    ua: Option<&UserAgent>,
+    purpose: Option<&str>,
+    ip: Option<IpAddr>,
+    bot_networks: &[IpNet],
+) -> ClickKind {
+    if ip.is_some_and(|ip| bot_networks.iter().any(|net| net.contains(&ip))) {
+        return ClickKind::Bot;
+    }
+    // browsers always identify themselves
+    match ua {
+        Some(ua) if ua.device != "bot" => {}
+        _ => return ClickKind::Bot,
+    }
+
+    let purpose = purpose.unwrap_or_default().to_lowercase();
+    if purpose.contains("prefetch") || purpose.contains("preview") {
+        return ClickKind::Prefetch;
+    }
+    ClickKind::Human
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{client_ip, user_agent};
+
+    use super::*;
+
+    const CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    fn kind(ua: Option<&str>, purpose: Option<&str>, ip: &str, bot_ips: &str) -> ClickKind {
+        let ua = ua.map(user_agent::parse);
+        classify(
+            ua.as_ref(),
+            purpose,
+            Some(ip.parse().unwrap()),
+            &client_ip::parse_networks(bot_ips).unwrap(),
+        )
+    }
+
+    #[test]
+    fn browsers_are_human() {
+        assert_eq!(
+            kind(Some(CHROME), None, "203.0.113.7", ""),
+            ClickKind::Human
+        );
+    }
+
+    #[test]
+    fn crawlers_and_unfurlers_are_bots() {
+        for ua in [
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+            "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)",
+            "facebookexternalhit/1.1",
+            "curl/8.4.0",
+            "python-requests/2.31",
+        ] {
+            assert_eq!(
+                kind(Some(ua), None, "203.0.113.7", ""),
+                ClickKind::Bot,
+                "{ua}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_user_agent_is_a_bot() {
+        assert_eq!(kind(None, None, "203.0.113.7", ""), ClickKind::Bot);
+    }
+
+    #[test]
+    fn bot_networks_win_over_a_browser_user_agent() {
+        let bot_ips = "198.51.100.0/24, 2001:db8::/32";
+        assert_eq!(
+            kind(Some(CHROME), None, "198.51.100.20", bot_ips),
+            ClickKind::Bot
+        );
+        assert_eq!(
+            kind(Some(CHROME), None, "2001:db8::1", bot_ips),
+            ClickKind::Bot
+        );
+        assert_eq!(
+            kind(Some(CHROME), None, "203.0.113.7", bot_ips),
+            ClickKind::Human
+        );
+    }
+
+    #[test]
+    fn purpose_headers_mark_prefetches() {
+        for purpose in ["prefetch", "Prefetch;prerender", "preview"] {
+            assert_eq!(
+                kind(Some(CHROME), Some(purpose), "203.0.113.7", ""),
+                ClickKind::Prefetch,
+                "{purpose}"
+            );
+        }
+        // a bot that prefetches is still a bot
+        assert_eq!(
+            kind(Some("curl/8.4.0"), Some("prefetch"), "203.0.113.7", ""),
+            ClickKind::Bot
+        );
+    }
+}
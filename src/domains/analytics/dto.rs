@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{export::ExportFormat, models::ClickEvent};
+
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct UrlStatsQuery {
+    /// First day of the range, defaults to 30 days up to `to`, or just `to` for hourly stats
+    #[param(example = "2025-05-01", format = Date)]
+    pub from: Option<String>,
+    /// Last day of the range, inclusive, defaults to today
+    #[param(example = "2025-05-31", format = Date)]
+    pub to: Option<String>,
+    /// `day` or `hour`, defaults to `day`
+    #[param(example = "day")]
+    pub granularity: Option<String>,
+    /// IANA time zone the days and hours are counted in, defaults to UTC
+    #[param(example = "Europe/Berlin")]
+    pub timezone: Option<String>,
+    /// How many entries the top lists hold, defaults to 10
+    #[param(example = 10)]
+    pub top: Option<i64>,
+    /// Count bot and prefetch clicks too, by default only human visits are
+    #[serde(default)]
+    pub include_bots: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct StatsPoint {
+    /// Start of the day or hour in the requested time zone
+    #[schema(example = "2025-05-01")]
+    pub bucket: String,
+    pub clicks: i64,
+    pub unique_visitors: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct StatsCount {
+    /// `null` when unknown, for referrers it means a direct visit
+    pub value: Option<String>,
+    pub clicks: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlStatsResponse {
+    pub from: String,
+    pub to: String,
+    pub granularity: String,
+    pub timezone: String,
+    pub include_bots: bool,
+    pub total_clicks: i64,
+    /// Distinct visitor addresses in the range
+    pub unique_visitors: i64,
+    pub series: Vec<StatsPoint>,
+    /// Referring hosts
+    pub top_referrers: Vec<StatsCount>,
+    pub top_countries: Vec<StatsCount>,
+    /// `mobile`, `tablet`, `desktop` or `bot`
+    pub top_devices: Vec<StatsCount>,
+    pub top_browsers: Vec<StatsCount>,
+    pub top_os: Vec<StatsCount>,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct ClickExportQuery {
+    /// `csv` or `ndjson`, defaults to `csv`
+    #[param(example = "csv")]
+    pub format: Option<String>,
+    /// First day of the range, defaults to 30 days up to `to`
+    #[param(example = "2025-05-01", format = Date)]
+    pub from: Option<String>,
+    /// Last day of the range, inclusive, defaults to today
+    #[param(example = "2025-05-31", format = Date)]
+    pub to: Option<String>,
+    /// IANA time zone the days are counted in, defaults to UTC
+    #[param(example = "Europe/Berlin")]
+    pub timezone: Option<String>,
+    /// Export bot and prefetch clicks too, by default only human visits are
+    #[serde(default)]
+    pub include_bots: bool,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct OverviewQuery {
+    /// First day of the period, defaults to 30 days up to `to`
+    #[param(example = "2025-05-01", format = Date)]
+    pub from: Option<String>,
+    /// Last day of the period, inclusive, defaults to today
+    #[param(example = "2025-05-31", format = Date)]
+    pub to: Option<String>,
+    /// IANA time zone the days are counted in, defaults to UTC
+    #[param(example = "Europe/Berlin")]
+    pub timezone: Option<String>,
+    /// How many links the top lists hold, defaults to 10
+    #[param(example = 10)]
+    pub top: Option<i64>,
+    /// Only favourite links, or only the others
+    pub favourite: Option<bool>,
+    /// Count bot and prefetch clicks too, by default only human visits are
+    #[serde(default)]
+    pub include_bots: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LinkClicks {
+    pub id: String,
+    pub short_url: String,
+    pub url: String,
+    pub favourite: bool,
+    pub clicks: i64,
+    /// Clicks in the period of the same length just before
+    pub previous_clicks: i64,
+    /// `clicks` minus `previous_clicks`
+    pub growth: i64,
+    /// Growth in percent, `null` when the link had no clicks before
+    pub growth_rate: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct OverviewResponse {
+    pub from: String,
+    pub to: String,
+    /// Start of the period compared against, it ends the day before `from`
+    pub previous_from: String,
+    pub timezone: String,
+    pub include_bots: bool,
+    /// Links the overview covers
+    pub links: i64,
+    pub total_clicks: i64,
+    pub previous_total_clicks: i64,
+    pub top_links: Vec<LinkClicks>,
+    /// Links that gained the most clicks over the previous period
+    pub fastest_growing: Vec<LinkClicks>,
+    /// Links nobody clicked in the period
+    pub without_clicks: Vec<LinkClicks>,
+}
+
+/// Raw clicks on their way to the client.
+pub struct ClickExport {
+    pub format: ExportFormat,
+    pub filename: String,
+    pub rows: ReceiverStream<Result<ClickEvent, sqlx::Error>>,
+}
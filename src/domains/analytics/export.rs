@@ -0,0 +1,180 @@
+use chrono::SecondsFormat;
+
+use super::models::ClickEvent;
+
+const CSV_HEADER: &str = "clicked_at,url_id,short_code,kind,referrer,user_agent,browser,os,device,country,visitor,variant_id\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "csv" => Some(ExportFormat::Csv),
+            "ndjson" => Some(ExportFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    /// Written once before the first row.
+    pub fn header(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => CSV_HEADER,
+            ExportFormat::Ndjson => "",
+        }
+    }
+
+    pub fn row(&self, click: &ClickEvent) -> String {
+        match self {
+            ExportFormat::Csv => csv_row(click),
+            ExportFormat::Ndjson => {
+                let mut line = serde_json::to_string(click).unwrap_or_default();
+                line.push('\n');
+                line
+            }
+        }
+    }
+}
+
+/// One RFC 4180 record.
+fn csv_row(click: &ClickEvent) -> String {
+    let fields = [
+        click
+            .clicked_at
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+        click.url_id.to_string(),
+        click.short_code.clone(),
+        click.kind.clone(),
+        click.referrer.clone().unwrap_or_default(),
+        click.user_agent.clone().unwrap_or_default(),
+        click.browser.clone().unwrap_or_default(),
+        click.os.clone().unwrap_or_default(),
+        click.device.clone().unwrap_or_default(),
+        click.country.clone().unwrap_or_default(),
+        click.visitor.clone().unwrap_or_default(),
+        click
+            .variant_id
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+    ];
+    let mut row = fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+fn csv_field(value: &str) -> String {
+    // a leading =, +, - or @ makes spreadsheets evaluate the cell as a formula
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn click() -> ClickEvent {
+        ClickEvent {
+            clicked_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+            url_id: Uuid::nil(),
+            short_code: "abc123".to_string(),
+            kind: "human".to_string(),
+            referrer: Some("https://example.com/a?b=1,2".to_string()),
+            user_agent: Some("say \"hi\"".to_string()),
+            browser: Some("Chrome".to_string()),
+            os: None,
+            device: None,
+            country: Some("DE".to_string()),
+            visitor: None,
+            variant_id: None,
+        }
+    }
+
+    #[test]
+    fn plain_fields_are_left_alone() {
+        assert_eq!(csv_field("Chrome"), "Chrome");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn fields_with_separators_are_quoted() {
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn formulas_are_defused() {
+        assert_eq!(csv_field("=1+1"), "'=1+1");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(
+            csv_field("=HYPERLINK(\"x\",\"y\")"),
+            "\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\""
+        );
+    }
+
+    #[test]
+    fn csv_rows_match_the_header() {
+        let row = ExportFormat::Csv.row(&click());
+        assert_eq!(
+            row,
+            "2024-05-01T12:30:00.000Z,00000000-0000-0000-0000-000000000000,abc123,human,\
+             \"https://example.com/a?b=1,2\",\"say \"\"hi\"\"\",Chrome,,,DE,,\r\n"
+        );
+        assert_eq!(
+            CSV_HEADER.split(',').count(),
+            ExportFormat::Csv
+                .row(&ClickEvent {
+                    referrer: None,
+                    user_agent: None,
+                    ..click()
+                })
+                .split(',')
+                .count()
+        );
+    }
+
+    #[test]
+    fn ndjson_rows_are_one_object_per_line() {
+        let row = ExportFormat::Ndjson.row(&click());
+        assert!(row.ends_with('\n'));
+        assert_eq!(row.matches('\n').count(), 1);
+        let value: serde_json::Value = serde_json::from_str(&row).unwrap();
+        assert_eq!(value["short_code"], "abc123");
+        assert_eq!(value["clicked_at"], "2024-05-01T12:30:00Z");
+        assert!(value["os"].is_null());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod classify;
+pub mod dto;
+pub mod export;
+pub mod models;
+pub mod service;
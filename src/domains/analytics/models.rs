@@ -0,0 +1,100 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::classify::ClickKind;
+
+#[derive(Debug, Clone)]
+pub struct NewClick {
+    pub url_id: Uuid,
+    pub clicked_at: NaiveDateTime,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    /// Salted hash of the visitor's address, the address itself is never stored
+    pub ip_hash: Option<String>,
+    pub country: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device: Option<String>,
+    pub variant_id: Option<Uuid>,
+    pub kind: ClickKind,
+    /// The link's click count was already raised on the redirect, which is
+    /// only done for click-limited links
+    pub counted: bool,
+}
+
+/// Clicks between `from` and `to` (exclusive), both local to `timezone`.
+#[derive(Debug, Clone)]
+pub struct ClickRange {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub timezone: String,
+    /// Count bot and prefetch clicks too, not just human visits
+    pub include_bots: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClickTotals {
+    pub clicks: i64,
+    pub unique_visitors: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClickBucket {
+    pub bucket: NaiveDateTime,
+    pub clicks: i64,
+    pub unique_visitors: i64,
+}
+
+/// Click attribute the top lists are grouped by.
+#[derive(Debug, Clone, Copy)]
+pub enum ClickDimension {
+    /// Host of the referring page
+    Referrer,
+    Country,
+    Browser,
+    Os,
+    Device,
+}
+
+impl ClickDimension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClickDimension::Referrer => "referrer",
+            ClickDimension::Country => "country",
+            ClickDimension::Browser => "browser",
+            ClickDimension::Os => "os",
+            ClickDimension::Device => "device",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClickCount {
+    pub value: Option<String>,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrlClicks {
+    pub url_id: Uuid,
+    pub clicks: i64,
+}
+
+/// A raw click as exported, with the short code of its link.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClickEvent {
+    pub clicked_at: DateTime<Utc>,
+    pub url_id: Uuid,
+    pub short_code: String,
+    pub kind: String,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device: Option<String>,
+    pub country: Option<String>,
+    /// Salted hash of the visitor's address
+    pub visitor: Option<String>,
+    pub variant_id: Option<Uuid>,
+}
@@ -0,0 +1,372 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{Days, NaiveDate, NaiveTime};
+use uuid::Uuid;
+
+use crate::{
+    common::errors::AppError,
+    config::config::Config,
+    domains::urls::models::Url,
+    infra::repositories::{
+        clicks::{interface::ClickRepository, repository::ClickRepo},
+        urls::{interface::UrlRepository, repository::UrlRepo},
+    },
+};
+
+use super::{
+    dto::{
+        ClickExport, ClickExportQuery, LinkClicks, OverviewQuery, OverviewResponse, StatsCount,
+        StatsPoint, UrlStatsQuery, UrlStatsResponse,
+    },
+    export::ExportFormat,
+    models::{ClickDimension, ClickRange},
+};
+
+const DEFAULT_STATS_DAYS: u64 = 30;
+const DEFAULT_HOURLY_STATS_DAYS: u64 = 1;
+const MAX_STATS_DAYS: i64 = 366;
+const MAX_HOURLY_STATS_DAYS: i64 = 31;
+const DEFAULT_STATS_TOP: i64 = 10;
+const MAX_STATS_TOP: i64 = 100;
+
+/// Reporting on top of the recorded clicks.
+#[derive(Clone)]
+pub struct AnalyticsService {
+    click_repo: Arc<dyn ClickRepository + Send + Sync>,
+    url_repo: Arc<dyn UrlRepository + Send + Sync>,
+    prefix: String,
+}
+
+impl AnalyticsService {
+    pub fn new(click_repo: Arc<ClickRepo>, url_repo: UrlRepo, config: &Config) -> Self {
+        AnalyticsService {
+            click_repo,
+            url_repo: Arc::new(url_repo),
+            prefix: config.service_host.clone(),
+        }
+    }
+
+    pub async fn get_url_stats(
+        &self,
+        id: &String,
+        user_id: &String,
+        query: &UrlStatsQuery,
+    ) -> Result<UrlStatsResponse, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+
+        let granularity = query
+            .granularity
+            .clone()
+            .unwrap_or_else(|| "day".to_string());
+        let (default_days, max_days) = match granularity.as_str() {
+            "day" => (DEFAULT_STATS_DAYS, MAX_STATS_DAYS),
+            "hour" => (DEFAULT_HOURLY_STATS_DAYS, MAX_HOURLY_STATS_DAYS),
+            _ => {
+                return Err(AppError::ValidationError(
+                    "Granularity must be day or hour".to_string(),
+                ));
+            }
+        };
+        let range = self
+            .click_range(
+                &query.from,
+                &query.to,
+                &query.timezone,
+                query.include_bots,
+                default_days,
+            )
+            .await?;
+        if (range.to - range.from).num_days() > max_days {
+            return Err(AppError::ValidationError(format!(
+                "Range can span at most {} days at {} granularity",
+                max_days, granularity
+            )));
+        }
+        let top = query
+            .top
+            .unwrap_or(DEFAULT_STATS_TOP)
+            .clamp(1, MAX_STATS_TOP);
+
+        let totals = self.click_repo.get_click_totals(&url.id, &range).await?;
+        let series = self
+            .click_repo
+            .get_click_series(&url.id, &range, &granularity)
+            .await?;
+        let top_referrers = self
+            .top(&url.id, &range, ClickDimension::Referrer, &top)
+            .await?;
+        let top_countries = self
+            .top(&url.id, &range, ClickDimension::Country, &top)
+            .await?;
+        let top_devices = self
+            .top(&url.id, &range, ClickDimension::Device, &top)
+            .await?;
+        let top_browsers = self
+            .top(&url.id, &range, ClickDimension::Browser, &top)
+            .await?;
+        let top_os = self.top(&url.id, &range, ClickDimension::Os, &top).await?;
+
+        let bucket_format = match granularity.as_str() {
+            "hour" => "%Y-%m-%dT%H:00",
+            _ => "%Y-%m-%d",
+        };
+        Ok(UrlStatsResponse {
+            from: range.from.date().to_string(),
+            to: (range.to.date() - Days::new(1)).to_string(),
+            granularity,
+            timezone: range.timezone.clone(),
+            include_bots: range.include_bots,
+            total_clicks: totals.clicks,
+            unique_visitors: totals.unique_visitors,
+            series: series
+                .into_iter()
+                .map(|point| StatsPoint {
+                    bucket: point.bucket.format(bucket_format).to_string(),
+                    clicks: point.clicks,
+                    unique_visitors: point.unique_visitors,
+                })
+                .collect(),
+            top_referrers,
+            top_countries,
+            top_devices,
+            top_browsers,
+            top_os,
+        })
+    }
+
+    /// Clicks across the user's links in the period, compared with the period
+    /// of the same length just before it.
+    pub async fn get_overview(
+        &self,
+        user_id: &String,
+        query: &OverviewQuery,
+    ) -> Result<OverviewResponse, AppError> {
+        let range = self
+            .click_range(
+                &query.from,
+                &query.to,
+                &query.timezone,
+                query.include_bots,
+                DEFAULT_STATS_DAYS,
+            )
+            .await?;
+        let days = (range.to - range.from).num_days();
+        if days > MAX_STATS_DAYS {
+            return Err(AppError::ValidationError(format!(
+                "Range can span at most {} days",
+                MAX_STATS_DAYS
+            )));
+        }
+        let previous = ClickRange {
+            from: range.from - Days::new(days as u64),
+            to: range.from,
+            ..range.clone()
+        };
+        let top = query
+            .top
+            .unwrap_or(DEFAULT_STATS_TOP)
+            .clamp(1, MAX_STATS_TOP) as usize;
+
+        let urls: Vec<Url> = self
+            .url_repo
+            .get_user_urls(user_id)
+            .await?
+            .into_iter()
+            .filter(|url| {
+                query
+                    .favourite
+                    .is_none_or(|favourite| url.favourite == favourite)
+            })
+            .collect();
+        let ids: Vec<Uuid> = urls.iter().map(|url| url.id).collect();
+        let clicks = self.clicks_per_url(&ids, &range).await?;
+        let previous_clicks = self.clicks_per_url(&ids, &previous).await?;
+
+        let links: Vec<LinkClicks> = urls
+            .into_iter()
+            .map(|url| {
+                let now = clicks.get(&url.id).copied().unwrap_or(0);
+                let before = previous_clicks.get(&url.id).copied().unwrap_or(0);
+                LinkClicks {
+                    id: url.id.to_string(),
+                    short_url: format!("{}/{}", self.prefix, url.short_url),
+                    url: url.url,
+                    favourite: url.favourite,
+                    clicks: now,
+                    previous_clicks: before,
+                    growth: now - before,
+                    growth_rate: (before > 0)
+                        .then(|| (now - before) as f64 * 100.0 / before as f64),
+                }
+            })
+            .collect();
+
+        let total_clicks = links.iter().map(|link| link.clicks).sum();
+        let previous_total_clicks = links.iter().map(|link| link.previous_clicks).sum();
+        let link_count = links.len() as i64;
+
+        let (mut active, mut without_clicks): (Vec<LinkClicks>, Vec<LinkClicks>) =
+            links.into_iter().partition(|link| link.clicks > 0);
+        without_clicks.sort_by(|a, b| a.short_url.cmp(&b.short_url));
+
+        active.sort_by(|a, b| {
+            (b.growth, b.clicks, &a.short_url).cmp(&(a.growth, a.clicks, &b.short_url))
+        });
+        let fastest_growing: Vec<LinkClicks> = active
+            .iter()
+            .filter(|link| link.growth > 0)
+            .take(top)
+            .cloned()
+            .collect();
+
+        active.sort_by(|a, b| (b.clicks, &a.short_url).cmp(&(a.clicks, &b.short_url)));
+        active.truncate(top);
+
+        Ok(OverviewResponse {
+            from: range.from.date().to_string(),
+            to: (range.to.date() - Days::new(1)).to_string(),
+            previous_from: previous.from.date().to_string(),
+            timezone: range.timezone,
+            include_bots: range.include_bots,
+            links: link_count,
+            total_clicks,
+            previous_total_clicks,
+            top_links: active,
+            fastest_growing,
+            without_clicks,
+        })
+    }
+
+    async fn clicks_per_url(
+        &self,
+        ids: &[Uuid],
+        range: &ClickRange,
+    ) -> Result<HashMap<Uuid, i64>, AppError> {
+        let clicks = self.click_repo.get_clicks_per_url(ids, range).await?;
+        Ok(clicks
+            .into_iter()
+            .map(|count| (count.url_id, count.clicks))
+            .collect())
+    }
+
+    /// Streams raw clicks on one link, or on all of the user's links when `id`
+    /// is `None`.
+    pub async fn export_clicks(
+        &self,
+        id: Option<&String>,
+        user_id: &String,
+        query: &ClickExportQuery,
+    ) -> Result<ClickExport, AppError> {
+        let format = match &query.format {
+            Some(format) => ExportFormat::parse(format).ok_or_else(|| {
+                AppError::ValidationError("Format must be csv or ndjson".to_string())
+            })?,
+            None => ExportFormat::Csv,
+        };
+        let owner = Uuid::parse_str(user_id).map_err(|_| AppError::InvalidToken)?;
+        let url = match id {
+            Some(id) => Some(self.get_owned_url(id, user_id).await?),
+            None => None,
+        };
+        let range = self
+            .click_range(
+                &query.from,
+                &query.to,
+                &query.timezone,
+                query.include_bots,
+                DEFAULT_STATS_DAYS,
+            )
+            .await?;
+
+        let filename = format!(
+            "clicks-{}-{}-{}.{}",
+            url.as_ref().map_or("all", |url| url.short_url.as_str()),
+            range.from.date(),
+            range.to.date() - Days::new(1),
+            format.extension()
+        );
+        Ok(ClickExport {
+            format,
+            filename,
+            rows: self
+                .click_repo
+                .stream_clicks(&owner, url.map(|url| url.id), &range),
+        })
+    }
+
+    /// Whole days from `from` to `to` in the time zone, `to` defaults to
+    /// today and `from` to `default_days` before it.
+    async fn click_range(
+        &self,
+        from: &Option<String>,
+        to: &Option<String>,
+        timezone: &Option<String>,
+        include_bots: bool,
+        default_days: u64,
+    ) -> Result<ClickRange, AppError> {
+        let timezone = timezone.clone().unwrap_or_else(|| "UTC".to_string());
+        let today = self
+            .click_repo
+            .local_date(&timezone)
+            .await?
+            .ok_or_else(|| AppError::ValidationError("Unknown time zone".to_string()))?;
+
+        let to = match to {
+            Some(to) => Self::parse_stats_date(to)?,
+            None => today,
+        };
+        let from = match from {
+            Some(from) => Self::parse_stats_date(from)?,
+            None => to - Days::new(default_days - 1),
+        };
+        if from > to {
+            return Err(AppError::ValidationError(
+                "from must not be after to".to_string(),
+            ));
+        }
+
+        Ok(ClickRange {
+            from: from.and_time(NaiveTime::MIN),
+            to: (to + Days::new(1)).and_time(NaiveTime::MIN),
+            timezone,
+            include_bots,
+        })
+    }
+
+    fn parse_stats_date(date: &str) -> Result<NaiveDate, AppError> {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| AppError::ValidationError("Dates must look like 2025-05-31".to_string()))
+    }
+
+    async fn top(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+        dimension: ClickDimension,
+        top: &i64,
+    ) -> Result<Vec<StatsCount>, AppError> {
+        let counts = self
+            .click_repo
+            .get_top_values(url_id, range, &dimension, top)
+            .await?;
+        Ok(counts
+            .into_iter()
+            .map(|count| StatsCount {
+                value: count.value,
+                clicks: count.clicks,
+            })
+            .collect())
+    }
+
+    async fn get_owned_url(&self, id: &String, user_id: &String) -> Result<Url, AppError> {
+        let url = self
+            .url_repo
+            .get_url_by_id(id)
+            .await
+            .map_err(|_| AppError::NotFound("URL not found".to_string()))?;
+        if url.user_id.map(|owner| owner.to_string()).as_ref() != Some(user_id) {
+            return Err(AppError::NotFound("URL not found".to_string()));
+        }
+        Ok(url)
+    }
+}
@@ -0,0 +1,52 @@
+use url::{Url, form_urlencoded};
+
+/// Appends the visitor's extra path segments to the destination path.
+/// `.`/`..` and empty segments are dropped so the result stays under the
+/// destination path.
+pub fn append_path(dest: &mut Url, extra: &str) {
+    let segments: Vec<&str> = extra
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+        .collect();
+    if segments.is_empty() {
+        return;
+    }
+
+    if let Ok(mut path) = dest.path_segments_mut() {
+        path.pop_if_empty().extend(segments);
+    }
+}
+
+/// Merges `pairs` into the destination query. Keys already present on the
+/// destination are replaced, everything else is kept in order.
+pub fn merge_query<I, K, V>(dest: &mut Url, pairs: I)
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let incoming: Vec<(String, String)> = pairs
+        .into_iter()
+        .map(|(k, v)| (k.as_ref().to_string(), v.as_ref().to_string()))
+        .collect();
+    if incoming.is_empty() {
+        return;
+    }
+
+    let kept: Vec<(String, String)> = dest
+        .query_pairs()
+        .filter(|(k, _)| !incoming.iter().any(|(key, _)| key == k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+
+    dest.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(incoming);
+}
+
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    form_urlencoded::parse(query.as_bytes())
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
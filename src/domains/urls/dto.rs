@@ -1,10 +1,37 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::models::{RuleConditions, UtmParams};
+
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UrlRequest {
     pub url: String,
+    #[schema(example = "q3-launch")]
+    pub alias: Option<String>,
+    #[schema(example = 302)]
+    pub redirect_type: Option<u16>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub active_from: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[schema(example = 1)]
+    pub max_clicks: Option<i32>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub forward_query: bool,
+    #[serde(default)]
+    pub forward_path: bool,
+    pub utm: Option<UtmParams>,
+    pub utm_preset_id: Option<String>,
+    /// App URI opened on iOS and Android, `url` stays the web fallback
+    #[schema(example = "myapp://item/42")]
+    pub deep_link: Option<String>,
+    /// Lets search engines index the link, unset follows the deployment default
+    pub allow_indexing: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
@@ -12,6 +39,20 @@ pub struct UrlResponse {
     pub id: String,
     pub url: String,
     pub short_url: String,
+    pub is_alias: bool,
+    pub redirect_type: u16,
+    pub active_from: Option<String>,
+    pub expires_at: Option<String>,
+    pub click_count: i32,
+    pub remaining_clicks: Option<i32>,
+    pub password_protected: bool,
+    pub warn: bool,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm: Option<UtmParams>,
+    pub utm_preset_id: Option<String>,
+    pub deep_link: Option<String>,
+    pub allow_indexing: Option<bool>,
     pub favourite: bool,
     pub deleted: bool,
     pub created_at: String,
@@ -21,3 +62,162 @@ pub struct UrlResponse {
 pub struct FavouriteUrl {
     pub favourite: bool,
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct WarnUrl {
+    pub warn: bool,
+    /// Shown to visitors on the warning page
+    #[schema(example = "Reported as phishing")]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UtmPresetRequest {
+    #[schema(example = "newsletter")]
+    pub name: String,
+    pub params: UtmParams,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UtmPresetResponse {
+    pub id: String,
+    pub name: String,
+    pub params: UtmParams,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlRuleRequest {
+    pub conditions: RuleConditions,
+    #[schema(example = "https://apps.apple.com/app/id0000000000")]
+    pub target_url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlRuleResponse {
+    pub id: String,
+    pub position: i32,
+    pub conditions: RuleConditions,
+    pub target_url: String,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlVariantRequest {
+    /// Id of an existing variant to keep it and its visit count, new
+    /// variants leave it out
+    pub id: Option<String>,
+    #[schema(example = "https://example.com/landing-b")]
+    pub target_url: String,
+    /// Relative share of the traffic, e.g. 70 and 30
+    #[schema(example = 70)]
+    pub weight: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlVariantsRequest {
+    pub variants: Vec<UrlVariantRequest>,
+    /// Keep returning visitors on the variant they got first
+    #[serde(default)]
+    pub sticky: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlVariantResponse {
+    pub id: String,
+    pub target_url: String,
+    pub weight: i32,
+    pub visit_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UrlVariantsResponse {
+    pub sticky: bool,
+    pub variants: Vec<UrlVariantResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UnlockUrl {
+    pub password: Option<String>,
+    /// Set by the warning page once the visitor chose to continue
+    #[serde(default)]
+    pub acknowledged: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VisitPath {
+    pub code: String,
+    pub rest: Option<String>,
+}
+
+/// What the redirect endpoint knows about the incoming request.
+#[derive(Debug, Clone, Default)]
+pub struct VisitContext {
+    pub extra_path: Option<String>,
+    pub query: Option<String>,
+    pub user_agent: Option<String>,
+    pub accept_language: Option<String>,
+    pub referrer: Option<String>,
+    /// `Sec-Purpose`, `Purpose` or `X-Purpose` header, set on prefetches
+    pub purpose: Option<String>,
+    pub client_ip: Option<IpAddr>,
+    /// Variant id from the visitor's sticky variant cookie
+    pub variant: Option<String>,
+    /// `/{code}+` was requested, show where the link goes instead of following it
+    pub preview: bool,
+    /// Resolve the visit as usual but don't count it, used for HEAD requests
+    pub dry_run: bool,
+}
+
+/// Where a visit ends up.
+pub struct Landing {
+    pub location: String,
+    /// Variant the visitor should be pinned to, only set for sticky links.
+    pub sticky_variant: Option<String>,
+    pub indexable: bool,
+}
+
+pub struct LinkPreview {
+    /// Empty for click-limited links
+    pub destinations: Vec<PreviewDestination>,
+    pub created_at: String,
+    pub owner: Option<String>,
+}
+
+pub struct PreviewDestination {
+    pub url: String,
+    /// Percentage of visits sent here, set when the link rotates variants
+    pub share: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DeadLinkFallback {
+    Redirect(String),
+    Page(String),
+}
+
+pub enum Visit {
+    Redirect {
+        landing: Landing,
+        redirect_type: u16,
+    },
+    PasswordRequired,
+    NotYetActive(String),
+    Warning(Option<String>),
+    Preview(LinkPreview),
+    /// The code never existed (`gone` false) or the link was deleted, expired
+    /// or used up (`gone` true).
+    DeadLink {
+        gone: bool,
+        fallback: Option<DeadLinkFallback>,
+    },
+    /// Mobile visit to a link with a deep link, the landing is the web fallback.
+    DeepLink {
+        deep_link: String,
+        landing: Landing,
+    },
+    /// A visit that doesn't use up a click on a click-limited link, answered
+    /// without the destination.
+    Withheld,
+}
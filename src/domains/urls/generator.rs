@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rand::{Rng, distr::Alphanumeric};
+
+use crate::{
+    common::errors::AppError, config::config::Config,
+    infra::repositories::urls::interface::UrlRepository,
+};
+
+const BASE62: &str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// odd and not a multiple of 31, so it is coprime with every power of 62
+const COUNTER_MULTIPLIER: u128 = 0x9E37_79B9_7F4A_7C15;
+
+#[async_trait]
+pub trait ShortCodeGenerator: Send + Sync {
+    /// `attempt` starts at 0 and is bumped by the caller every time the previous
+    /// code turned out to be taken.
+    async fn generate(&self, url: &str, attempt: u32) -> Result<String, AppError>;
+}
+
+pub fn from_config(
+    config: &Config,
+    url_repo: Arc<dyn UrlRepository + Send + Sync>,
+) -> Arc<dyn ShortCodeGenerator> {
+    let length = config.short_code_length;
+    match config.short_code_strategy.as_str() {
+        "counter" => Arc::new(CounterGenerator::new(
+            url_repo,
+            &config.short_code_salt,
+            length,
+        )),
+        "hash" => Arc::new(HashGenerator::new(&config.short_code_salt, length)),
+        // anything else is rejected when the config is loaded
+        _ => Arc::new(RandomGenerator::new(length)),
+    }
+}
+
+pub struct RandomGenerator {
+    length: usize,
+}
+
+impl RandomGenerator {
+    pub fn new(length: usize) -> Self {
+        RandomGenerator { length }
+    }
+}
+
+#[async_trait]
+impl ShortCodeGenerator for RandomGenerator {
+    async fn generate(&self, _url: &str, _attempt: u32) -> Result<String, AppError> {
+        let code = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(self.length)
+            .map(char::from)
+            .collect();
+        Ok(code)
+    }
+}
+
+/// Encodes a database sequence with a salted alphabet, similar to sqids.
+/// Numbers are scrambled inside the `min_length` space so consecutive links
+/// don't get consecutive codes.
+pub struct CounterGenerator {
+    url_repo: Arc<dyn UrlRepository + Send + Sync>,
+    alphabet: Vec<char>,
+    min_length: usize,
+}
+
+impl CounterGenerator {
+    pub fn new(
+        url_repo: Arc<dyn UrlRepository + Send + Sync>,
+        salt: &str,
+        min_length: usize,
+    ) -> Self {
+        CounterGenerator {
+            url_repo,
+            alphabet: shuffled_alphabet(salt),
+            min_length,
+        }
+    }
+
+    pub fn encode(&self, n: u64) -> String {
+        let base = self.alphabet.len() as u128;
+        let space = base.checked_pow(self.min_length as u32);
+
+        let mut n = n as u128;
+        let mut length = 0;
+        if let Some(space) = space.filter(|space| n < *space) {
+            n = (n * COUNTER_MULTIPLIER) % space;
+            length = self.min_length;
+        }
+
+        let mut code = Vec::new();
+        while n > 0 || code.len() < length {
+            code.push(self.alphabet[(n % base) as usize]);
+            n /= base;
+        }
+        if code.is_empty() {
+            code.push(self.alphabet[0]);
+        }
+        code.iter().rev().collect()
+    }
+}
+
+#[async_trait]
+impl ShortCodeGenerator for CounterGenerator {
+    async fn generate(&self, _url: &str, _attempt: u32) -> Result<String, AppError> {
+        let n = self.url_repo.next_code_sequence().await?;
+        Ok(self.encode(n as u64))
+    }
+}
+
+/// Salted md5 of the url. Retries feed the attempt number back into the hash
+/// so a taken code yields a different one.
+pub struct HashGenerator {
+    salt: String,
+    length: usize,
+}
+
+impl HashGenerator {
+    pub fn new(salt: &str, length: usize) -> Self {
+        HashGenerator {
+            salt: salt.to_string(),
+            length: length.min(32),
+        }
+    }
+}
+
+#[async_trait]
+impl ShortCodeGenerator for HashGenerator {
+    async fn generate(&self, url: &str, attempt: u32) -> Result<String, AppError> {
+        let hash = format!(
+            "{:x}",
+            md5::compute(format!("{}:{}:{}", self.salt, url, attempt))
+        );
+        Ok(hash[..self.length].to_string())
+    }
+}
+
+fn shuffled_alphabet(salt: &str) -> Vec<char> {
+    let mut alphabet: Vec<char> = BASE62.chars().collect();
+    if salt.is_empty() {
+        return alphabet;
+    }
+
+    let digest = md5::compute(salt.as_bytes());
+    for i in (1..alphabet.len()).rev() {
+        let j = (digest[i % digest.len()] as usize + i) % (i + 1);
+        alphabet.swap(i, j);
+    }
+    alphabet
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use crate::infra::repositories::urls::repository::UrlRepo;
+
+    use super::*;
+
+    // `encode` never touches the repository, the pool is never connected
+    fn counter(salt: &str, min_length: usize) -> CounterGenerator {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        CounterGenerator::new(Arc::new(UrlRepo::new(pool)), salt, min_length)
+    }
+
+    #[tokio::test]
+    async fn counter_codes_are_unique_inside_the_min_length_space() {
+        let generator = counter("salt", 2);
+        let codes: HashSet<String> = (0..62 * 62).map(|n| generator.encode(n)).collect();
+        assert_eq!(codes.len(), 62 * 62);
+        assert!(codes.iter().all(|code| code.len() == 2));
+    }
+
+    #[tokio::test]
+    async fn counter_codes_grow_past_the_min_length_space() {
+        let generator = counter("salt", 2);
+        let inside: HashSet<String> = (0..62 * 62).map(|n| generator.encode(n)).collect();
+        let past: HashSet<String> = (62 * 62..62 * 62 * 3)
+            .map(|n| generator.encode(n))
+            .collect();
+        assert_eq!(past.len(), 62 * 62 * 2);
+        assert!(past.iter().all(|code| code.len() == 3));
+        assert!(inside.is_disjoint(&past));
+    }
+
+    #[tokio::test]
+    async fn consecutive_counters_get_unrelated_codes() {
+        let generator = counter("salt", 6);
+        assert_ne!(generator.encode(1)[..5], generator.encode(2)[..5]);
+    }
+
+    #[test]
+    fn salted_alphabet_is_a_permutation() {
+        let mut alphabet = shuffled_alphabet("salt");
+        assert_ne!(alphabet, BASE62.chars().collect::<Vec<_>>());
+        alphabet.sort();
+        let mut base: Vec<char> = BASE62.chars().collect();
+        base.sort();
+        assert_eq!(alphabet, base);
+    }
+
+    #[tokio::test]
+    async fn hash_codes_change_with_the_attempt() {
+        let generator = HashGenerator::new("salt", 8);
+        let first = generator.generate("https://example.com", 0).await.unwrap();
+        let again = generator.generate("https://example.com", 0).await.unwrap();
+        let retry = generator.generate("https://example.com", 1).await.unwrap();
+        assert_eq!(first.len(), 8);
+        assert_eq!(first, again);
+        assert_ne!(first, retry);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod destination;
 pub mod dto;
+pub mod generator;
 pub mod models;
+pub mod rules;
 pub mod service;
+pub mod variants;
@@ -1,5 +1,6 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -9,7 +10,136 @@ pub struct Url {
     pub user_id: Option<Uuid>,
     pub url: String,
     pub short_url: String,
+    pub is_alias: bool,
+    pub redirect_type: Option<i16>,
+    pub active_from: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_clicks: Option<i32>,
+    pub click_count: i32,
+    pub password_hash: Option<String>,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm_params: Option<Json<UtmParams>>,
+    pub utm_preset_id: Option<Uuid>,
+    pub sticky_variants: bool,
+    pub warn: bool,
+    pub warn_reason: Option<String>,
+    pub deep_link: Option<String>,
+    pub allow_indexing: Option<bool>,
     pub favourite: bool,
     pub deleted: bool,
     pub created_at: NaiveDateTime,
 }
+
+#[derive(Debug, Clone)]
+pub struct NewUrl {
+    pub url: String,
+    pub short_url: String,
+    pub is_alias: bool,
+    pub redirect_type: Option<i16>,
+    pub active_from: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_clicks: Option<i32>,
+    pub password_hash: Option<String>,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm_params: Option<Json<UtmParams>>,
+    pub utm_preset_id: Option<Uuid>,
+    pub deep_link: Option<String>,
+    pub allow_indexing: Option<bool>,
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct UtmParams {
+    #[schema(example = "newsletter")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm_source: Option<String>,
+    #[schema(example = "email")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm_medium: Option<String>,
+    #[schema(example = "q3-launch")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm_campaign: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm_term: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utm_id: Option<String>,
+}
+
+impl UtmParams {
+    pub fn pairs(&self) -> Vec<(&'static str, &String)> {
+        [
+            ("utm_source", &self.utm_source),
+            ("utm_medium", &self.utm_medium),
+            ("utm_campaign", &self.utm_campaign),
+            ("utm_term", &self.utm_term),
+            ("utm_content", &self.utm_content),
+            ("utm_id", &self.utm_id),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_ref().map(|v| (key, v)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UtmPreset {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub params: Json<UtmParams>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Conditions of a routing rule. Every condition that is set has to match,
+/// a list matches when any of its entries does.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RuleConditions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = json!(["ios"]))]
+    pub os: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = json!(["mobile", "tablet"]))]
+    pub device: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browser: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = json!(["de", "en-GB"]))]
+    pub language: Option<Vec<String>>,
+    /// ISO 3166-1 alpha-2 country codes, resolved from the visitor's IP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = json!(["DE", "AT"]))]
+    pub country: Option<Vec<String>>,
+    /// Start of a daily UTC window, e.g. `09:00:00`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "09:00:00")]
+    pub time_from: Option<NaiveTime>,
+    /// End of a daily UTC window, may be before `time_from` to wrap midnight
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "17:00:00")]
+    pub time_to: Option<NaiveTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlRule {
+    pub id: Uuid,
+    pub url_id: Uuid,
+    pub position: i32,
+    pub conditions: Json<RuleConditions>,
+    pub target_url: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlVariant {
+    pub id: Uuid,
+    pub url_id: Uuid,
+    pub position: i32,
+    pub target_url: String,
+    pub weight: i32,
+    pub visit_count: i64,
+    pub created_at: NaiveDateTime,
+}
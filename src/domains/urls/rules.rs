@@ -0,0 +1,263 @@
+use chrono::NaiveTime;
+
+use crate::common::user_agent::UserAgent;
+
+use super::models::{RuleConditions, UrlRule};
+
+/// The request attributes rules are evaluated against.
+pub struct RuleInput {
+    pub user_agent: Option<UserAgent>,
+    pub language: Option<String>,
+    pub country: Option<String>,
+    pub time: NaiveTime,
+}
+
+/// Returns the first rule, in position order, whose conditions all match.
+pub fn first_match<'a>(rules: &'a [UrlRule], input: &RuleInput) -> Option<&'a UrlRule> {
+    rules.iter().find(|rule| matches(&rule.conditions, input))
+}
+
+pub fn matches(conditions: &RuleConditions, input: &RuleInput) -> bool {
+    let ua = input.user_agent.as_ref();
+    matches_any(&conditions.os, ua.map(|ua| ua.os.as_str()))
+        && matches_any(&conditions.device, ua.map(|ua| ua.device.as_str()))
+        && matches_any(&conditions.browser, ua.map(|ua| ua.browser.as_str()))
+        && matches_language(&conditions.language, input.language.as_deref())
+        && matches_any(&conditions.country, input.country.as_deref())
+        && in_window(conditions.time_from, conditions.time_to, input.time)
+}
+
+fn matches_any(expected: &Option<Vec<String>>, actual: Option<&str>) -> bool {
+    match expected {
+        None => true,
+        Some(values) => actual.is_some_and(|actual| {
+            values
+                .iter()
+                .any(|value| value.eq_ignore_ascii_case(actual))
+        }),
+    }
+}
+
+/// `de` matches `de`, `de-AT` and `de-DE`, `de-AT` only matches itself.
+fn matches_language(expected: &Option<Vec<String>>, actual: Option<&str>) -> bool {
+    match expected {
+        None => true,
+        Some(values) => actual.is_some_and(|actual| {
+            let actual = actual.to_lowercase();
+            values.iter().any(|value| {
+                let value = value.to_lowercase();
+                actual == value || actual.starts_with(&format!("{}-", value))
+            })
+        }),
+    }
+}
+
+fn in_window(from: Option<NaiveTime>, to: Option<NaiveTime>, time: NaiveTime) -> bool {
+    match (from, to) {
+        (None, None) => true,
+        (Some(from), None) => time >= from,
+        (None, Some(to)) => time < to,
+        (Some(from), Some(to)) if from <= to => time >= from && time < to,
+        (Some(from), Some(to)) => time >= from || time < to,
+    }
+}
+
+/// Picks the language with the highest weight from an `Accept-Language` header.
+pub fn preferred_language(accept_language: &str) -> Option<String> {
+    accept_language
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.trim().split(';');
+            let tag = pieces.next()?.trim();
+            if tag.is_empty() || tag == "*" {
+                return None;
+            }
+            let weight = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((tag.to_string(), weight))
+        })
+        .filter(|(_, weight)| *weight > 0.0)
+        // the earlier tag wins between equal weights
+        .fold(None::<(String, f32)>, |best, (tag, weight)| match best {
+            Some((_, best_weight)) if best_weight >= weight => best,
+            _ => Some((tag, weight)),
+        })
+        .map(|(tag, _)| tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    use crate::common::user_agent;
+
+    use super::*;
+
+    const IPHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+
+    fn at(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    fn input() -> RuleInput {
+        RuleInput {
+            user_agent: Some(user_agent::parse(IPHONE)),
+            language: Some("de-AT".to_string()),
+            country: Some("AT".to_string()),
+            time: at("12:00"),
+        }
+    }
+
+    fn list(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|v| v.to_string()).collect())
+    }
+
+    fn rule(position: i32, conditions: RuleConditions) -> UrlRule {
+        UrlRule {
+            id: Uuid::new_v4(),
+            url_id: Uuid::nil(),
+            position,
+            conditions: Json(conditions),
+            target_url: format!("https://example.com/{}", position),
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn every_set_condition_has_to_match() {
+        let conditions = RuleConditions {
+            os: list(&["ios"]),
+            country: list(&["AT", "DE"]),
+            ..Default::default()
+        };
+        assert!(matches(&conditions, &input()));
+
+        let conditions = RuleConditions {
+            os: list(&["ios"]),
+            country: list(&["DE"]),
+            ..Default::default()
+        };
+        assert!(!matches(&conditions, &input()));
+    }
+
+    #[test]
+    fn lists_match_case_insensitively() {
+        let conditions = RuleConditions {
+            os: list(&["iOS"]),
+            country: list(&["at"]),
+            ..Default::default()
+        };
+        assert!(matches(&conditions, &input()));
+    }
+
+    #[test]
+    fn missing_attributes_never_match_a_condition() {
+        let input = RuleInput {
+            user_agent: None,
+            country: None,
+            ..input()
+        };
+        let conditions = RuleConditions {
+            device: list(&["mobile"]),
+            ..Default::default()
+        };
+        assert!(!matches(&conditions, &input));
+        let conditions = RuleConditions {
+            country: list(&["AT"]),
+            ..Default::default()
+        };
+        assert!(!matches(&conditions, &input));
+    }
+
+    #[test]
+    fn language_prefix_matches_regional_variants_only_one_way() {
+        let prefix = RuleConditions {
+            language: list(&["de"]),
+            ..Default::default()
+        };
+        assert!(matches(&prefix, &input()));
+
+        let regional = RuleConditions {
+            language: list(&["de-DE"]),
+            ..Default::default()
+        };
+        assert!(!matches(&regional, &input()));
+
+        let other = RuleConditions {
+            language: list(&["d"]),
+            ..Default::default()
+        };
+        assert!(!matches(&other, &input()));
+    }
+
+    #[test]
+    fn time_windows_include_the_start_and_exclude_the_end() {
+        assert!(in_window(Some(at("09:00")), Some(at("17:00")), at("09:00")));
+        assert!(!in_window(
+            Some(at("09:00")),
+            Some(at("17:00")),
+            at("17:00")
+        ));
+        assert!(in_window(Some(at("09:00")), None, at("23:59")));
+        assert!(!in_window(None, Some(at("09:00")), at("10:00")));
+    }
+
+    #[test]
+    fn time_windows_can_wrap_midnight() {
+        let (from, to) = (Some(at("22:00")), Some(at("06:00")));
+        assert!(in_window(from, to, at("23:00")));
+        assert!(in_window(from, to, at("05:59")));
+        assert!(!in_window(from, to, at("12:00")));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = vec![
+            rule(
+                0,
+                RuleConditions {
+                    os: list(&["android"]),
+                    ..Default::default()
+                },
+            ),
+            rule(
+                1,
+                RuleConditions {
+                    country: list(&["AT"]),
+                    ..Default::default()
+                },
+            ),
+            rule(
+                2,
+                RuleConditions {
+                    os: list(&["ios"]),
+                    ..Default::default()
+                },
+            ),
+        ];
+        let matched = first_match(&rules, &input()).unwrap();
+        assert_eq!(matched.position, 1);
+
+        let input = RuleInput {
+            user_agent: None,
+            country: None,
+            ..input()
+        };
+        assert!(first_match(&rules, &input).is_none());
+    }
+
+    #[test]
+    fn preferred_language_picks_the_highest_weight() {
+        assert_eq!(
+            preferred_language("en;q=0.5, de-AT, fr;q=0.8").as_deref(),
+            Some("de-AT")
+        );
+        assert_eq!(preferred_language("en, de").as_deref(), Some("en"));
+        assert_eq!(preferred_language("*, de;q=0").as_deref(), None);
+        assert_eq!(preferred_language("").as_deref(), None);
+    }
+}
@@ -1,47 +1,223 @@
-use std::sync::Arc;
+use std::{fs, net::IpAddr, sync::Arc, time::Duration};
 
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
+};
 use axum::Error;
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
 use regex::Regex;
+use sha2::Sha256;
+use sqlx::types::Json;
+use tracing::warn;
 use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    common::{constants, errors::AppError},
+    common::{
+        client_ip, constants,
+        errors::{AppError, is_unique_violation},
+        rate_limit::AttemptLimiter,
+        user_agent::{self, UserAgent},
+    },
     config::config::Config,
-    infra::repositories::{
-        urls::{interface::UrlRepository, repository::UrlRepo},
-        users::{interface::UserRepository, repository::UsersRepo},
+    domains::analytics::{
+        classify::{self, ClickKind},
+        models::NewClick,
+    },
+    infra::{
+        click_buffer::ClickBuffer,
+        geoip::GeoIp,
+        repositories::{
+            urls::{interface::UrlRepository, repository::UrlRepo},
+            users::{interface::UserRepository, repository::UsersRepo},
+        },
     },
 };
 
-use super::dto::UrlResponse;
+use super::{
+    destination,
+    dto::{
+        DeadLinkFallback, Landing, LinkPreview, PreviewDestination, UnlockUrl, UrlRequest,
+        UrlResponse, UrlRuleRequest, UrlRuleResponse, UrlVariantResponse, UrlVariantsRequest,
+        UrlVariantsResponse, UtmPresetRequest, UtmPresetResponse, Visit, VisitContext, WarnUrl,
+    },
+    generator::{self, ShortCodeGenerator},
+    models::{self, NewUrl, RuleConditions, UtmParams},
+    rules::{self, RuleInput},
+    variants,
+};
+
+const MAX_CODE_ATTEMPTS: u32 = 5;
+const MAX_RULES_PER_URL: usize = 50;
+const MAX_VARIANTS_PER_URL: usize = 10;
+const MAX_VARIANT_WEIGHT: i32 = 10_000;
+const MAX_WARN_REASON_LENGTH: usize = 500;
+
+/// A code that doesn't lead anywhere, with the owner whose fallback applies.
+struct DeadLink {
+    /// The link existed but was deleted, expired or used up
+    gone: bool,
+    owner: Option<Uuid>,
+}
+
+impl DeadLink {
+    /// Answered like a code that never existed, so the visitor can't tell
+    /// there is a link behind it.
+    fn not_found() -> Self {
+        DeadLink {
+            gone: false,
+            owner: None,
+        }
+    }
+}
+
+enum LinkLookup {
+    Live(Box<models::Url>),
+    Dead(DeadLink),
+}
 
 #[derive(Clone)]
 pub struct UrlService {
     url_repo: Arc<dyn UrlRepository + Send + Sync>,
+    clicks: ClickBuffer,
     user_repo: Arc<dyn UserRepository + Send + Sync>,
+    code_generator: Arc<dyn ShortCodeGenerator>,
     prefix: String,
+    default_redirect_type: u16,
+    unlock_attempts: Arc<AttemptLimiter<Uuid>>,
+    inactive_placeholder: bool,
+    geoip: Arc<GeoIp>,
+    admin_users: Vec<String>,
+    deep_link_schemes: Vec<String>,
+    dead_link_fallback: Option<DeadLinkFallback>,
+    allow_indexing: bool,
+    click_ip_salt: String,
+    bot_networks: Vec<IpNet>,
 }
 
 impl UrlService {
-    pub fn new(url_repo: UrlRepo, user_repo: Arc<UsersRepo>, config: &Config) -> Self {
+    pub fn new(
+        url_repo: UrlRepo,
+        clicks: ClickBuffer,
+        user_repo: Arc<UsersRepo>,
+        geoip: Arc<GeoIp>,
+        config: &Config,
+    ) -> Self {
+        let url_repo: Arc<dyn UrlRepository + Send + Sync> = Arc::new(url_repo);
         UrlService {
-            url_repo: Arc::new(url_repo),
+            code_generator: generator::from_config(config, Arc::clone(&url_repo)),
+            url_repo,
+            clicks,
             user_repo: user_repo,
             prefix: config.service_host.clone(),
+            default_redirect_type: config.default_redirect_type,
+            unlock_attempts: Arc::new(AttemptLimiter::new(
+                config.link_password_max_attempts,
+                Duration::from_secs(config.link_password_lockout_secs),
+            )),
+            inactive_placeholder: config.inactive_link_response == "placeholder",
+            geoip,
+            admin_users: config
+                .admin_users
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            deep_link_schemes: config
+                .deep_link_schemes
+                .split(',')
+                .map(|scheme| scheme.trim().to_lowercase())
+                .filter(|scheme| !scheme.is_empty())
+                .collect(),
+            dead_link_fallback: Self::load_dead_link_fallback(config),
+            allow_indexing: config.allow_indexing,
+            click_ip_salt: config.click_ip_salt.clone(),
+            bot_networks: client_ip::parse_networks(&config.bot_ips)
+                .expect("BOT_IPS is checked by Config::validate"),
+        }
+    }
+
+    fn load_dead_link_fallback(config: &Config) -> Option<DeadLinkFallback> {
+        if let Some(url) = &config.dead_link_fallback_url {
+            return Some(DeadLinkFallback::Redirect(url.clone()));
+        }
+        let path = config.dead_link_page.as_ref()?;
+        match fs::read_to_string(path) {
+            Ok(page) => Some(DeadLinkFallback::Page(page)),
+            Err(e) => {
+                warn!("Dead link page {} not loaded: {}", path, e);
+                None
+            }
         }
     }
 
     pub async fn shorten_url(
         &self,
-        url: &String,
+        payload: &UrlRequest,
         user_id: &String,
     ) -> Result<UrlResponse, AppError> {
         //check for safety of the URL
         //<CODE>
-        let parsed = Url::parse(url)
-            .map_err(|_| AppError::ValidationError("URL Error".to_string()))?
-            .to_string();
+        // app schemes go in `deep_link`, the url itself has to open in a browser
+        let parsed = Self::check_target_url(&payload.url, "URL")?;
+
+        let deep_link = match &payload.deep_link {
+            Some(deep_link) => Some(self.check_deep_link(deep_link)?),
+            None => None,
+        };
+
+        if payload
+            .redirect_type
+            .is_some_and(|t| !constants::REDIRECT_TYPES.contains(&t))
+        {
+            return Err(AppError::ValidationError(format!(
+                "Redirect type must be one of {:?}",
+                constants::REDIRECT_TYPES
+            )));
+        }
+
+        if payload.expires_at.is_some_and(|t| t <= Utc::now()) {
+            return Err(AppError::ValidationError(
+                "Expiry must be in the future".to_string(),
+            ));
+        }
+
+        if payload
+            .active_from
+            .zip(payload.expires_at)
+            .is_some_and(|(active_from, expires_at)| active_from >= expires_at)
+        {
+            return Err(AppError::ValidationError(
+                "Activation time must be before expiry".to_string(),
+            ));
+        }
+
+        if payload.max_clicks.is_some_and(|n| n < 1) {
+            return Err(AppError::ValidationError(
+                "Max clicks must be at least 1".to_string(),
+            ));
+        }
+
+        let password_hash = match &payload.password {
+            Some(password) => {
+                if password.len() < 4 || password.len() > 64 {
+                    return Err(AppError::ValidationError(
+                        "Password must be between 4 and 64 characters".to_string(),
+                    ));
+                }
+                let salt = SaltString::generate(&mut OsRng);
+                let argon2 = Argon2::default();
+                Some(
+                    argon2
+                        .hash_password(password.as_bytes(), &salt)?
+                        .to_string(),
+                )
+            }
+            None => None,
+        };
 
         let user = self
             .user_repo
@@ -49,70 +225,570 @@ impl UrlService {
             .await
             .map_err(|_| AppError::NotFound("User not found".to_string()))?;
 
-        let hash = format!("{:x}", md5::compute(parsed.clone()));
-        let short_url = &hash[..8].to_string();
-        match self.url_repo.create(&parsed, short_url, &user.id).await {
-            Ok(url) => {
-                return Ok(UrlResponse {
-                    id: url.id.to_string(),
-                    url: url.url,
-                    short_url: format!("{}/{}", self.prefix, url.short_url),
-                    favourite: url.favourite,
-                    deleted: false,
-                    created_at: url.created_at.to_string(),
-                });
+        if let Some(utm) = &payload.utm {
+            Self::check_utm_params(utm)?;
+        }
+
+        let utm_preset_id = match &payload.utm_preset_id {
+            Some(preset_id) => {
+                let preset_id = Uuid::parse_str(preset_id)
+                    .map_err(|_| AppError::ValidationError("Invalid UTM preset id".to_string()))?;
+                let preset = self
+                    .url_repo
+                    .get_utm_preset(&preset_id)
+                    .await
+                    .map_err(|_| AppError::NotFound("UTM preset not found".to_string()))?;
+                if preset.user_id != user.id {
+                    return Err(AppError::NotFound("UTM preset not found".to_string()));
+                }
+                Some(preset.id)
             }
-            Err(e) => {
-                println!("{:?}", e);
-                return Err(AppError::DatabaseError(e));
+            None => None,
+        };
+
+        let mut new_url = NewUrl {
+            url: parsed,
+            short_url: String::new(),
+            is_alias: false,
+            redirect_type: payload.redirect_type.map(|t| t as i16),
+            active_from: payload.active_from.map(|t| t.naive_utc()),
+            expires_at: payload.expires_at.map(|t| t.naive_utc()),
+            max_clicks: payload.max_clicks,
+            password_hash,
+            forward_query: payload.forward_query,
+            forward_path: payload.forward_path,
+            utm_params: payload.utm.clone().map(Json),
+            utm_preset_id,
+            deep_link,
+            allow_indexing: payload.allow_indexing,
+            user_id: user.id,
+        };
+
+        let url = match &payload.alias {
+            Some(alias) => {
+                self.check_alias(alias).await?;
+                new_url.short_url = alias.clone();
+                new_url.is_alias = true;
+                self.url_repo.create(&new_url).await.map_err(|e| {
+                    if is_unique_violation(&e) {
+                        AppError::Conflict(format!("Alias '{}' is already taken", alias))
+                    } else {
+                        AppError::DatabaseError(e)
+                    }
+                })?
+            }
+            None => self.create_with_generated_code(new_url).await?,
+        };
+
+        Ok(self.to_response(url))
+    }
+
+    /// Parses a url visitors get sent to. Only http and https are allowed,
+    /// a `javascript:` or `data:` url would run on the short link's origin
+    /// from the deep link bridge page.
+    fn check_target_url(url: &String, label: &str) -> Result<String, AppError> {
+        let parsed =
+            Url::parse(url).map_err(|_| AppError::ValidationError(format!("{} Error", label)))?;
+        if !constants::WEB_SCHEMES.contains(&parsed.scheme()) {
+            return Err(AppError::ValidationError(format!(
+                "{} must use http or https",
+                label
+            )));
+        }
+        Ok(parsed.to_string())
+    }
+
+    fn check_deep_link(&self, deep_link: &String) -> Result<String, AppError> {
+        let parsed = Url::parse(deep_link)
+            .map_err(|_| AppError::ValidationError("Deep link Error".to_string()))?;
+        let scheme = parsed.scheme();
+        if constants::BLOCKED_DEEP_LINK_SCHEMES.contains(&scheme) {
+            return Err(AppError::ValidationError(format!(
+                "Deep link can't use the {} scheme",
+                scheme
+            )));
+        }
+        if !self.deep_link_schemes.is_empty() && !self.deep_link_schemes.iter().any(|s| s == scheme)
+        {
+            return Err(AppError::ValidationError(format!(
+                "Deep link scheme must be one of {:?}",
+                self.deep_link_schemes
+            )));
+        }
+        Ok(parsed.to_string())
+    }
+
+    async fn create_with_generated_code(
+        &self,
+        mut new_url: NewUrl,
+    ) -> Result<models::Url, AppError> {
+        for attempt in 0..MAX_CODE_ATTEMPTS {
+            new_url.short_url = self.code_generator.generate(&new_url.url, attempt).await?;
+            if constants::RESERVED_CODES.contains(&new_url.short_url.to_lowercase().as_str()) {
+                continue;
+            }
+
+            match self.url_repo.create(&new_url).await {
+                Ok(url) => return Ok(url),
+                Err(e) if is_unique_violation(&e) => continue,
+                Err(e) => return Err(AppError::DatabaseError(e)),
             }
         }
+
+        tracing::error!("Could not generate a free short code for {}", new_url.url);
+        Err(AppError::InternalError)
     }
 
     pub async fn delete_url(&self, id: &String) -> Result<UrlResponse, AppError> {
         match self.url_repo.delete(id).await {
-            Ok(url) => {
-                return Ok(UrlResponse {
-                    id: url.id.to_string(),
-                    url: url.url,
-                    short_url: url.short_url,
-                    favourite: url.favourite,
-                    deleted: true,
-                    created_at: url.created_at.to_string(),
-                });
-            }
+            Ok(url) => Ok(self.to_response(url)),
             Err(e) => Err(AppError::DatabaseError(e)),
         }
     }
 
-    pub async fn enter_url(&self, short_url: &String) -> Result<UrlResponse, AppError> {
-        match self.url_repo.get_url_by_short_url(short_url).await {
-            Ok(url) => {
-                return Ok(UrlResponse {
-                    id: url.id.to_string(),
-                    url: url.url,
-                    short_url: url.short_url,
-                    favourite: url.favourite,
-                    deleted: false,
-                    created_at: url.created_at.to_string(),
-                });
+    pub async fn enter_url(
+        &self,
+        short_url: &String,
+        ctx: &VisitContext,
+    ) -> Result<Visit, AppError> {
+        let url = match self.lookup_link(short_url, ctx).await? {
+            LinkLookup::Live(url) => *url,
+            LinkLookup::Dead(dead) => return Ok(self.dead_link(dead).await),
+        };
+        let owner = url.user_id;
+        let visit = self.enter_live_url(url, ctx).await;
+        self.or_used_up(owner, visit).await
+    }
+
+    async fn enter_live_url(
+        &self,
+        url: models::Url,
+        ctx: &VisitContext,
+    ) -> Result<Visit, AppError> {
+        if let Some(active_from) = Self::pending_activation(&url) {
+            if !self.inactive_placeholder {
+                return Ok(self.dead_link(DeadLink::not_found()).await);
             }
-            Err(e) => Err(AppError::DatabaseError(e)),
+            return Ok(Visit::NotYetActive(active_from.to_string()));
+        }
+        // the preview only shows where the link goes, so it skips the warning
+        if url.warn && !ctx.preview {
+            return Ok(Visit::Warning(url.warn_reason));
+        }
+        if url.password_hash.is_some() {
+            return Ok(Visit::PasswordRequired);
+        }
+        if ctx.preview {
+            return Ok(Visit::Preview(self.preview(&url, ctx).await?));
+        }
+
+        self.visit(url, ctx).await
+    }
+
+    /// Counts the visit and decides between a plain redirect and the deep link
+    /// bridge page.
+    async fn visit(&self, url: models::Url, ctx: &VisitContext) -> Result<Visit, AppError> {
+        let redirect_type = self.redirect_type(&url);
+        let deep_link = url.deep_link.clone().filter(|_| Self::is_mobile(ctx));
+        let Some(landing) = self.follow(url, ctx).await? else {
+            return Ok(Visit::Withheld);
+        };
+        match deep_link {
+            Some(deep_link) => Ok(Visit::DeepLink { deep_link, landing }),
+            None => Ok(Visit::Redirect {
+                landing,
+                redirect_type,
+            }),
+        }
+    }
+
+    fn is_mobile(ctx: &VisitContext) -> bool {
+        ctx.user_agent
+            .as_deref()
+            .map(user_agent::parse)
+            .is_some_and(|ua| ua.device != "bot" && (ua.os == "ios" || ua.os == "android"))
+    }
+
+    /// Where a visit would go, without counting it as a click. Click-limited
+    /// links keep their destination to themselves, like they do for HEAD.
+    async fn preview(
+        &self,
+        url: &models::Url,
+        ctx: &VisitContext,
+    ) -> Result<LinkPreview, AppError> {
+        Self::check_clicks_left(url)?;
+
+        let destinations = match url.max_clicks {
+            Some(_) => Vec::new(),
+            None => self.preview_destinations(url, ctx).await?,
+        };
+        let owner = match &url.user_id {
+            Some(user_id) => self
+                .user_repo
+                .get_user_by_id(&user_id.to_string())
+                .await
+                .ok()
+                .map(|user| user.username),
+            None => None,
+        };
+
+        Ok(LinkPreview {
+            destinations,
+            created_at: url.created_at.format("%Y-%m-%d").to_string(),
+            owner,
+        })
+    }
+
+    /// Handles the forms of the warning and password pages. The warning has
+    /// to be acknowledged before the password is checked.
+    pub async fn unlock_url(
+        &self,
+        short_url: &String,
+        payload: &UnlockUrl,
+        ctx: &VisitContext,
+    ) -> Result<Visit, AppError> {
+        let url = match self.lookup_link(short_url, ctx).await? {
+            LinkLookup::Live(url) => *url,
+            LinkLookup::Dead(dead) => return Ok(self.dead_link(dead).await),
+        };
+        let owner = url.user_id;
+        let visit = self.unlock_live_url(url, payload, ctx).await;
+        self.or_used_up(owner, visit).await
+    }
+
+    async fn unlock_live_url(
+        &self,
+        url: models::Url,
+        payload: &UnlockUrl,
+        ctx: &VisitContext,
+    ) -> Result<Visit, AppError> {
+        if Self::pending_activation(&url).is_some() {
+            return Ok(self.dead_link(DeadLink::not_found()).await);
+        }
+        if url.warn && !payload.acknowledged {
+            return Ok(Visit::Warning(url.warn_reason));
+        }
+
+        if let Some(password_hash) = &url.password_hash {
+            let Some(password) = &payload.password else {
+                return Ok(Visit::PasswordRequired);
+            };
+            self.check_link_password(&url.id, password_hash, password)?;
+        }
+
+        self.visit(url, ctx).await
+    }
+
+    fn check_link_password(
+        &self,
+        url_id: &Uuid,
+        password_hash: &String,
+        password: &String,
+    ) -> Result<(), AppError> {
+        if self.unlock_attempts.is_locked(url_id) {
+            return Err(AppError::TooManyRequests(
+                "Too many failed attempts, try again later".to_string(),
+            ));
+        }
+
+        let argon2 = Argon2::default();
+        let parsed_hash = PasswordHash::new(password_hash)?;
+        if argon2
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_err()
+        {
+            self.unlock_attempts.record_failure(url_id);
+            return Err(AppError::ValidationError("Invalid password".to_string()));
+        }
+
+        self.unlock_attempts.reset(url_id);
+        Ok(())
+    }
+
+    /// A link that ran out of clicks while it was being visited gets the
+    /// dead link page as well.
+    async fn or_used_up(
+        &self,
+        owner: Option<Uuid>,
+        visit: Result<Visit, AppError>,
+    ) -> Result<Visit, AppError> {
+        match visit {
+            Err(AppError::Gone(_)) => Ok(self.dead_link(DeadLink { gone: true, owner }).await),
+            visit => visit,
+        }
+    }
+
+    /// The dead link page for the code. The owner's fallback wins over the
+    /// deployment's.
+    async fn dead_link(&self, dead: DeadLink) -> Visit {
+        let owner = match dead.owner {
+            Some(user_id) => self
+                .user_repo
+                .get_user_by_id(&user_id.to_string())
+                .await
+                .ok(),
+            None => None,
+        };
+        let fallback = owner
+            .and_then(|user| user.dead_link_url)
+            .map(DeadLinkFallback::Redirect)
+            .or_else(|| self.dead_link_fallback.clone());
+
+        Visit::DeadLink {
+            gone: dead.gone,
+            fallback,
+        }
+    }
+
+    /// Finds the link behind the code. Codes that never existed, links that
+    /// were deleted or expired and paths below links that don't forward them
+    /// come back as dead links.
+    async fn lookup_link(
+        &self,
+        short_url: &String,
+        ctx: &VisitContext,
+    ) -> Result<LinkLookup, AppError> {
+        let url = match self.url_repo.get_url_by_short_url(short_url).await? {
+            Some(url) => url,
+            None => return Ok(LinkLookup::Dead(DeadLink::not_found())),
+        };
+        if url.deleted || url.expires_at.is_some_and(|t| t <= Utc::now().naive_utc()) {
+            return Ok(LinkLookup::Dead(DeadLink {
+                gone: true,
+                owner: url.user_id,
+            }));
+        }
+        if ctx.extra_path.is_some() && !url.forward_path {
+            return Ok(LinkLookup::Dead(DeadLink::not_found()));
+        }
+        Ok(LinkLookup::Live(Box::new(url)))
+    }
+
+    fn pending_activation(url: &models::Url) -> Option<NaiveDateTime> {
+        url.active_from.filter(|t| *t > Utc::now().naive_utc())
+    }
+
+    /// Resolves where the visit should land and counts it, unless it came
+    /// from a bot or a prefetch. `None` when the destination is withheld.
+    async fn follow(
+        &self,
+        url: models::Url,
+        ctx: &VisitContext,
+    ) -> Result<Option<Landing>, AppError> {
+        let (location, variant) = self.destination(&url, ctx).await?;
+        let indexable = url.allow_indexing.unwrap_or(self.allow_indexing);
+        let ua = ctx.user_agent.as_deref().map(user_agent::parse);
+        let kind = classify::classify(
+            ua.as_ref(),
+            ctx.purpose.as_deref(),
+            ctx.client_ip,
+            &self.bot_networks,
+        );
+
+        // bots and prefetches only show up in analytics, an unfurler mustn't
+        // use up a link with a click limit. Only a limit needs the count raised
+        // right away, other clicks are counted when the click buffer flushes.
+        let counted = Self::counts_visit(ctx, kind) && url.max_clicks.is_some();
+        if counted {
+            if self.url_repo.register_visit(&url.id).await?.is_none() {
+                return Err(AppError::Gone(
+                    "Link has reached its click limit".to_string(),
+                ));
+            }
+        } else {
+            Self::check_clicks_left(&url)?;
+        }
+        if !ctx.dry_run {
+            self.record_click(&url, ctx, ua, kind, variant, counted);
+        }
+        if Self::withholds_destination(&url, ctx, kind) {
+            return Ok(None);
+        }
+        Ok(Some(Landing {
+            location,
+            sticky_variant: variant
+                .filter(|_| url.sticky_variants && !ctx.dry_run)
+                .map(|id| id.to_string()),
+            indexable,
+        }))
+    }
+
+    /// HEAD requests, bots and prefetches don't use up a click.
+    fn counts_visit(ctx: &VisitContext, kind: ClickKind) -> bool {
+        !ctx.dry_run && kind == ClickKind::Human
+    }
+
+    /// A click-limited link only gives its destination to visits that use up
+    /// a click. Whether a visit counts is up to headers the client picks, so
+    /// `curl -I` or a bot user agent could otherwise read a single-use link
+    /// forever.
+    fn withholds_destination(url: &models::Url, ctx: &VisitContext, kind: ClickKind) -> bool {
+        url.max_clicks.is_some() && !Self::counts_visit(ctx, kind)
+    }
+
+    /// Queues the click for analytics, it is written in the background.
+    fn record_click(
+        &self,
+        url: &models::Url,
+        ctx: &VisitContext,
+        ua: Option<UserAgent>,
+        kind: ClickKind,
+        variant: Option<Uuid>,
+        counted: bool,
+    ) {
+        let new_click = NewClick {
+            url_id: url.id,
+            clicked_at: Utc::now().naive_utc(),
+            referrer: ctx.referrer.clone(),
+            user_agent: ctx.user_agent.clone(),
+            ip_hash: ctx.client_ip.map(|ip| self.ip_hash(ip)),
+            country: ctx.client_ip.and_then(|ip| self.geoip.country(ip)),
+            browser: ua.as_ref().map(|ua| ua.browser.clone()),
+            os: ua.as_ref().map(|ua| ua.os.clone()),
+            device: ua.as_ref().map(|ua| ua.device.clone()),
+            variant_id: variant,
+            kind,
+            counted,
+        };
+        self.clicks.record(new_click);
+    }
+
+    /// Keyed so that the hashes can't be reversed by hashing every address.
+    fn ip_hash(&self, ip: IpAddr) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.click_ip_salt.as_bytes())
+            .expect("hmac accepts keys of any length");
+        mac.update(ip.to_string().as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn check_clicks_left(url: &models::Url) -> Result<(), AppError> {
+        if url.max_clicks.is_some_and(|max| url.click_count >= max) {
+            return Err(AppError::Gone(
+                "Link has reached its click limit".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the destination and the A/B variant it came from, if any.
+    /// A matching routing rule takes precedence over the variants.
+    async fn destination(
+        &self,
+        url: &models::Url,
+        ctx: &VisitContext,
+    ) -> Result<(String, Option<Uuid>), AppError> {
+        let (base, variant) = match self.rule_target(url, ctx).await? {
+            Some(target) => (target, None),
+            None => {
+                let all = self.url_repo.get_url_variants(&url.id).await?;
+                match variants::pick(&all, Self::pinned_variant(url, ctx)) {
+                    Some(picked) => (picked.target_url.clone(), Some(picked.id)),
+                    None => (url.url.clone(), None),
+                }
+            }
+        };
+        Ok((self.complete_destination(url, ctx, &base).await?, variant))
+    }
+
+    /// Every destination a visit could get. Without a matching rule or a
+    /// pinned variant that is each variant with its share of the traffic.
+    async fn preview_destinations(
+        &self,
+        url: &models::Url,
+        ctx: &VisitContext,
+    ) -> Result<Vec<PreviewDestination>, AppError> {
+        let all = match self.rule_target(url, ctx).await? {
+            Some(target) => {
+                let url = self.complete_destination(url, ctx, &target).await?;
+                return Ok(vec![PreviewDestination { url, share: None }]);
+            }
+            None => self.url_repo.get_url_variants(&url.id).await?,
+        };
+        let bases = match variants::pinned(&all, Self::pinned_variant(url, ctx)) {
+            Some(pinned) => vec![(pinned.target_url.clone(), None)],
+            None if all.is_empty() => vec![(url.url.clone(), None)],
+            None => all
+                .iter()
+                .map(|variant| {
+                    (
+                        variant.target_url.clone(),
+                        Some(variants::share(&all, variant)),
+                    )
+                })
+                .collect(),
+        };
+
+        let mut destinations = Vec::new();
+        for (base, share) in bases {
+            let url = self.complete_destination(url, ctx, &base).await?;
+            destinations.push(PreviewDestination { url, share });
+        }
+        Ok(destinations)
+    }
+
+    async fn rule_target(
+        &self,
+        url: &models::Url,
+        ctx: &VisitContext,
+    ) -> Result<Option<String>, AppError> {
+        let rules = self.url_repo.get_url_rules(&url.id).await?;
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        let input = RuleInput {
+            user_agent: ctx.user_agent.as_deref().map(user_agent::parse),
+            language: ctx
+                .accept_language
+                .as_deref()
+                .and_then(rules::preferred_language),
+            country: ctx.client_ip.and_then(|ip| self.geoip.country(ip)),
+            time: Utc::now().time(),
+        };
+        Ok(rules::first_match(&rules, &input).map(|rule| rule.target_url.clone()))
+    }
+
+    fn pinned_variant<'a>(url: &models::Url, ctx: &'a VisitContext) -> Option<&'a str> {
+        ctx.variant.as_deref().filter(|_| url.sticky_variants)
+    }
+
+    /// Forwards the visitor's path and query and adds the UTM params.
+    async fn complete_destination(
+        &self,
+        url: &models::Url,
+        ctx: &VisitContext,
+        base: &str,
+    ) -> Result<String, AppError> {
+        let mut dest = Url::parse(base).map_err(|_| AppError::InternalError)?;
+        if let Some(extra_path) = ctx.extra_path.as_ref().filter(|_| url.forward_path) {
+            destination::append_path(&mut dest, extra_path);
+        }
+        if let Some(query) = ctx.query.as_ref().filter(|_| url.forward_query) {
+            destination::merge_query(&mut dest, destination::parse_query(query));
+        }
+        // link level UTM params win over the preset and the visitor's query
+        if let Some(preset_id) = &url.utm_preset_id {
+            let preset = self.url_repo.get_utm_preset(preset_id).await?;
+            destination::merge_query(&mut dest, preset.params.pairs());
         }
+        if let Some(utm) = &url.utm_params {
+            destination::merge_query(&mut dest, utm.pairs());
+        }
+        Ok(dest.to_string())
+    }
+
+    fn redirect_type(&self, url: &models::Url) -> u16 {
+        url.redirect_type
+            .map(|t| t as u16)
+            .unwrap_or(self.default_redirect_type)
     }
 
     pub async fn favourite_url(&self, id: &String, state: &bool) -> Result<UrlResponse, AppError> {
         match self.url_repo.favourite_url(id, state).await {
-            Ok(url) => {
-                return Ok(UrlResponse {
-                    id: url.id.to_string(),
-                    url: url.url,
-                    short_url: url.short_url,
-                    favourite: url.favourite,
-                    deleted: false,
-                    created_at: url.created_at.to_string(),
-                });
-            }
+            Ok(url) => Ok(self.to_response(url)),
             Err(e) => {
                 println!("{:?}", e);
                 Err(AppError::DatabaseError(e))
@@ -120,23 +796,481 @@ impl UrlService {
         }
     }
 
+    pub async fn set_url_warning(
+        &self,
+        id: &String,
+        payload: &WarnUrl,
+        user_id: &String,
+    ) -> Result<UrlResponse, AppError> {
+        self.check_admin(user_id).await?;
+        if payload
+            .reason
+            .as_ref()
+            .is_some_and(|reason| reason.chars().count() > MAX_WARN_REASON_LENGTH)
+        {
+            return Err(AppError::ValidationError(format!(
+                "Reason can be at most {} characters",
+                MAX_WARN_REASON_LENGTH
+            )));
+        }
+
+        // clearing the flag drops the old reason as well
+        let reason = payload.reason.clone().filter(|_| payload.warn);
+        match self
+            .url_repo
+            .set_url_warning(id, &payload.warn, &reason)
+            .await
+        {
+            Ok(url) => Ok(self.to_response(url)),
+            Err(sqlx::Error::RowNotFound) => Err(AppError::NotFound("URL not found".to_string())),
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn check_admin(&self, user_id: &String) -> Result<(), AppError> {
+        let user = self.user_repo.get_user_by_id(user_id).await.ok();
+        if !user.is_some_and(|user| self.admin_users.contains(&user.username)) {
+            return Err(AppError::Forbidden("Admin access required".to_string()));
+        }
+        Ok(())
+    }
+
     pub async fn get_user_urls(&self, user_id: &String) -> Result<Vec<UrlResponse>, AppError> {
         match self.url_repo.get_user_urls(user_id).await {
-            Ok(urls) => {
-                let mut url_responses = Vec::new();
-                for url in urls {
-                    url_responses.push(UrlResponse {
-                        id: url.id.to_string(),
-                        url: url.url,
-                        short_url: format!("{}/{}", self.prefix, url.short_url),
-                        favourite: url.favourite,
-                        deleted: false,
-                        created_at: url.created_at.to_string(),
-                    });
+            Ok(urls) => Ok(urls.into_iter().map(|url| self.to_response(url)).collect()),
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
+    fn to_response(&self, url: models::Url) -> UrlResponse {
+        let redirect_type = self.redirect_type(&url);
+        UrlResponse {
+            id: url.id.to_string(),
+            url: url.url,
+            short_url: format!("{}/{}", self.prefix, url.short_url),
+            is_alias: url.is_alias,
+            redirect_type,
+            active_from: url.active_from.map(|t| t.to_string()),
+            expires_at: url.expires_at.map(|t| t.to_string()),
+            click_count: url.click_count,
+            remaining_clicks: url.max_clicks.map(|max| (max - url.click_count).max(0)),
+            password_protected: url.password_hash.is_some(),
+            warn: url.warn,
+            forward_query: url.forward_query,
+            forward_path: url.forward_path,
+            utm: url.utm_params.map(|utm| utm.0),
+            utm_preset_id: url.utm_preset_id.map(|id| id.to_string()),
+            deep_link: url.deep_link,
+            allow_indexing: url.allow_indexing,
+            favourite: url.favourite,
+            deleted: url.deleted,
+            created_at: url.created_at.to_string(),
+        }
+    }
+
+    pub async fn get_url_rules(
+        &self,
+        id: &String,
+        user_id: &String,
+    ) -> Result<Vec<UrlRuleResponse>, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+        let rules = self.url_repo.get_url_rules(&url.id).await?;
+        Ok(rules.into_iter().map(Self::to_rule_response).collect())
+    }
+
+    pub async fn add_url_rule(
+        &self,
+        id: &String,
+        user_id: &String,
+        payload: &UrlRuleRequest,
+    ) -> Result<UrlRuleResponse, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+        let target_url = Self::check_rule(payload)?;
+        if self.url_repo.get_url_rules(&url.id).await?.len() >= MAX_RULES_PER_URL {
+            return Err(AppError::ValidationError(format!(
+                "A link can have at most {} rules",
+                MAX_RULES_PER_URL
+            )));
+        }
+
+        let rule = self
+            .url_repo
+            .create_url_rule(&url.id, &payload.conditions, &target_url)
+            .await?;
+        Ok(Self::to_rule_response(rule))
+    }
+
+    pub async fn replace_url_rules(
+        &self,
+        id: &String,
+        user_id: &String,
+        payload: &[UrlRuleRequest],
+    ) -> Result<Vec<UrlRuleResponse>, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+        if payload.len() > MAX_RULES_PER_URL {
+            return Err(AppError::ValidationError(format!(
+                "A link can have at most {} rules",
+                MAX_RULES_PER_URL
+            )));
+        }
+
+        let mut new_rules = Vec::new();
+        for rule in payload {
+            new_rules.push((rule.conditions.clone(), Self::check_rule(rule)?));
+        }
+
+        let rules = self.url_repo.replace_url_rules(&url.id, &new_rules).await?;
+        Ok(rules.into_iter().map(Self::to_rule_response).collect())
+    }
+
+    pub async fn delete_url_rule(
+        &self,
+        id: &String,
+        rule_id: &String,
+        user_id: &String,
+    ) -> Result<UrlRuleResponse, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+        match self.url_repo.delete_url_rule(&url.id, rule_id).await {
+            Ok(rule) => Ok(Self::to_rule_response(rule)),
+            Err(sqlx::Error::RowNotFound) => Err(AppError::NotFound("Rule not found".to_string())),
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn get_url_variants(
+        &self,
+        id: &String,
+        user_id: &String,
+    ) -> Result<UrlVariantsResponse, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+        let variants = self.url_repo.get_url_variants(&url.id).await?;
+        Ok(Self::to_variants_response(url.sticky_variants, variants))
+    }
+
+    /// Replaces the link's variants. An empty list turns rotation off and
+    /// sends every visit to the link's own url again. Variants sent with
+    /// their id keep their visit count.
+    pub async fn replace_url_variants(
+        &self,
+        id: &String,
+        user_id: &String,
+        payload: &UrlVariantsRequest,
+    ) -> Result<UrlVariantsResponse, AppError> {
+        let url = self.get_owned_url(id, user_id).await?;
+        if payload.variants.len() == 1 || payload.variants.len() > MAX_VARIANTS_PER_URL {
+            return Err(AppError::ValidationError(format!(
+                "A link needs between 2 and {} variants",
+                MAX_VARIANTS_PER_URL
+            )));
+        }
+
+        let mut new_variants: Vec<(Option<Uuid>, String, i32)> = Vec::new();
+        for variant in &payload.variants {
+            let variant_id = match &variant.id {
+                Some(variant_id) => {
+                    let variant_id = Uuid::parse_str(variant_id)
+                        .map_err(|_| AppError::ValidationError("Invalid variant id".to_string()))?;
+                    if new_variants
+                        .iter()
+                        .any(|(id, _, _)| *id == Some(variant_id))
+                    {
+                        return Err(AppError::ValidationError(
+                            "Each variant id can only be used once".to_string(),
+                        ));
+                    }
+                    Some(variant_id)
                 }
-                return Ok(url_responses);
+                None => None,
+            };
+            let target_url = Self::check_target_url(&variant.target_url, "Variant URL")?;
+            if !(1..=MAX_VARIANT_WEIGHT).contains(&variant.weight) {
+                return Err(AppError::ValidationError(format!(
+                    "Variant weight must be between 1 and {}",
+                    MAX_VARIANT_WEIGHT
+                )));
+            }
+            new_variants.push((variant_id, target_url, variant.weight));
+        }
+
+        match self
+            .url_repo
+            .replace_url_variants(&url.id, &new_variants, payload.sticky)
+            .await
+        {
+            Ok(variants) => Ok(Self::to_variants_response(payload.sticky, variants)),
+            Err(sqlx::Error::RowNotFound) => {
+                Err(AppError::NotFound("Variant not found".to_string()))
             }
             Err(e) => Err(AppError::DatabaseError(e)),
         }
     }
+
+    fn to_variants_response(
+        sticky: bool,
+        variants: Vec<models::UrlVariant>,
+    ) -> UrlVariantsResponse {
+        UrlVariantsResponse {
+            sticky,
+            variants: variants
+                .into_iter()
+                .map(|variant| UrlVariantResponse {
+                    id: variant.id.to_string(),
+                    target_url: variant.target_url,
+                    weight: variant.weight,
+                    visit_count: variant.visit_count,
+                    created_at: variant.created_at.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    async fn get_owned_url(&self, id: &String, user_id: &String) -> Result<models::Url, AppError> {
+        let url = self
+            .url_repo
+            .get_url_by_id(id)
+            .await
+            .map_err(|_| AppError::NotFound("URL not found".to_string()))?;
+        if url.user_id.map(|owner| owner.to_string()).as_ref() != Some(user_id) {
+            return Err(AppError::NotFound("URL not found".to_string()));
+        }
+        Ok(url)
+    }
+
+    fn check_rule(rule: &UrlRuleRequest) -> Result<String, AppError> {
+        let target_url = Self::check_target_url(&rule.target_url, "Rule target URL")?;
+
+        let RuleConditions {
+            os,
+            device,
+            browser,
+            language,
+            country,
+            time_from,
+            time_to,
+        } = &rule.conditions;
+        if os.is_none()
+            && device.is_none()
+            && browser.is_none()
+            && language.is_none()
+            && country.is_none()
+            && time_from.is_none()
+            && time_to.is_none()
+        {
+            return Err(AppError::ValidationError(
+                "A rule needs at least one condition".to_string(),
+            ));
+        }
+        Ok(target_url)
+    }
+
+    fn to_rule_response(rule: models::UrlRule) -> UrlRuleResponse {
+        UrlRuleResponse {
+            id: rule.id.to_string(),
+            position: rule.position,
+            conditions: rule.conditions.0,
+            target_url: rule.target_url,
+            created_at: rule.created_at.to_string(),
+        }
+    }
+
+    pub async fn create_utm_preset(
+        &self,
+        payload: &UtmPresetRequest,
+        user_id: &String,
+    ) -> Result<UtmPresetResponse, AppError> {
+        if payload.name.trim().is_empty() || payload.name.len() > 64 {
+            return Err(AppError::ValidationError(
+                "Preset name must be between 1 and 64 characters".to_string(),
+            ));
+        }
+        Self::check_utm_params(&payload.params)?;
+
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|_| AppError::NotFound("User not found".to_string()))?;
+
+        match self
+            .url_repo
+            .create_utm_preset(&user.id, &payload.name, &payload.params)
+            .await
+        {
+            Ok(preset) => Ok(Self::to_preset_response(preset)),
+            Err(e) if is_unique_violation(&e) => Err(AppError::Conflict(format!(
+                "Preset '{}' already exists",
+                payload.name
+            ))),
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn get_utm_presets(
+        &self,
+        user_id: &String,
+    ) -> Result<Vec<UtmPresetResponse>, AppError> {
+        match self.url_repo.get_user_utm_presets(user_id).await {
+            Ok(presets) => Ok(presets.into_iter().map(Self::to_preset_response).collect()),
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn delete_utm_preset(
+        &self,
+        id: &String,
+        user_id: &String,
+    ) -> Result<UtmPresetResponse, AppError> {
+        match self.url_repo.delete_utm_preset(id, user_id).await {
+            Ok(preset) => Ok(Self::to_preset_response(preset)),
+            Err(sqlx::Error::RowNotFound) => {
+                Err(AppError::NotFound("UTM preset not found".to_string()))
+            }
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
+    fn to_preset_response(preset: models::UtmPreset) -> UtmPresetResponse {
+        UtmPresetResponse {
+            id: preset.id.to_string(),
+            name: preset.name,
+            params: preset.params.0,
+            created_at: preset.created_at.to_string(),
+        }
+    }
+
+    fn check_utm_params(params: &UtmParams) -> Result<(), AppError> {
+        let pairs = params.pairs();
+        if pairs.is_empty() {
+            return Err(AppError::ValidationError(
+                "At least one UTM parameter is required".to_string(),
+            ));
+        }
+        if let Some((key, _)) = pairs
+            .iter()
+            .find(|(_, value)| value.trim().is_empty() || value.len() > 256)
+        {
+            return Err(AppError::ValidationError(format!(
+                "{} must be between 1 and 256 characters",
+                key
+            )));
+        }
+        Ok(())
+    }
+
+    async fn check_alias(&self, alias: &str) -> Result<(), AppError> {
+        if !Self::validate_alias(alias) {
+            return Err(AppError::ValidationError(
+                "Alias must be 3-32 characters of letters, digits, '-' or '_'".to_string(),
+            ));
+        }
+
+        if constants::RESERVED_CODES.contains(&alias.to_lowercase().as_str()) {
+            return Err(AppError::ValidationError(format!(
+                "Alias '{}' is reserved",
+                alias
+            )));
+        }
+
+        if self.url_repo.short_url_exists(&alias.to_string()).await? {
+            return Err(AppError::Conflict(format!(
+                "Alias '{}' is already taken",
+                alias
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_alias(alias: &str) -> bool {
+        let rgx = Regex::new(constants::ALIAS_REGEX).unwrap();
+        rgx.is_match(alias)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(max_clicks: Option<i32>) -> models::Url {
+        models::Url {
+            id: Uuid::nil(),
+            user_id: None,
+            url: "https://example.com/download".to_string(),
+            short_url: "abc123".to_string(),
+            is_alias: false,
+            redirect_type: None,
+            active_from: None,
+            expires_at: None,
+            max_clicks,
+            click_count: 0,
+            password_hash: None,
+            forward_query: false,
+            forward_path: false,
+            utm_params: None,
+            utm_preset_id: None,
+            sticky_variants: false,
+            warn: false,
+            warn_reason: None,
+            deep_link: None,
+            allow_indexing: None,
+            favourite: false,
+            deleted: false,
+            created_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn head_requests_dont_see_where_a_single_use_link_goes() {
+        let head = VisitContext {
+            dry_run: true,
+            ..Default::default()
+        };
+        assert!(UrlService::withholds_destination(
+            &url(Some(1)),
+            &head,
+            ClickKind::Human
+        ));
+        assert!(!UrlService::withholds_destination(
+            &url(None),
+            &head,
+            ClickKind::Human
+        ));
+        assert!(!UrlService::withholds_destination(
+            &url(Some(1)),
+            &VisitContext::default(),
+            ClickKind::Human
+        ));
+    }
+
+    #[test]
+    fn bots_and_prefetches_dont_see_where_a_single_use_link_goes() {
+        let get = VisitContext::default();
+        for kind in [ClickKind::Bot, ClickKind::Prefetch] {
+            assert!(!UrlService::counts_visit(&get, kind));
+            assert!(UrlService::withholds_destination(&url(Some(1)), &get, kind));
+            // unlimited links still redirect them, they just aren't counted
+            assert!(!UrlService::withholds_destination(&url(None), &get, kind));
+        }
+        assert!(UrlService::counts_visit(&get, ClickKind::Human));
+    }
+
+    #[test]
+    fn target_urls_have_to_be_web_urls() {
+        for url in [
+            "javascript:alert(document.cookie)",
+            "JavaScript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "vbscript:msgbox(1)",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                UrlService::check_target_url(&url.to_string(), "URL").is_err(),
+                "{url}"
+            );
+        }
+        assert_eq!(
+            UrlService::check_target_url(&"https://example.com".to_string(), "URL").unwrap(),
+            "https://example.com/"
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use rand::Rng;
+
+use super::models::UrlVariant;
+
+/// Picks a variant with probability proportional to its weight. `pinned` is
+/// the variant the visitor got before, it wins as long as it still exists.
+pub fn pick<'a>(variants: &'a [UrlVariant], pinned: Option<&str>) -> Option<&'a UrlVariant> {
+    if let Some(variant) = self::pinned(variants, pinned) {
+        return Some(variant);
+    }
+
+    let total: i64 = variants.iter().map(|v| v.weight.max(0) as i64).sum();
+    if total == 0 {
+        return variants.first();
+    }
+
+    let mut roll = rand::rng().random_range(0..total);
+    variants.iter().find(|v| {
+        let weight = v.weight.max(0) as i64;
+        if roll < weight {
+            return true;
+        }
+        roll -= weight;
+        false
+    })
+}
+
+/// The variant the visitor was pinned to, if it still exists.
+pub fn pinned<'a>(variants: &'a [UrlVariant], id: Option<&str>) -> Option<&'a UrlVariant> {
+    let id = id?;
+    variants.iter().find(|v| v.id.to_string() == id)
+}
+
+/// Rounded percentage of visits `pick` sends to the variant.
+pub fn share(variants: &[UrlVariant], variant: &UrlVariant) -> u32 {
+    let total: i64 = variants.iter().map(|v| v.weight.max(0) as i64).sum();
+    if total == 0 {
+        return 0;
+    }
+    ((variant.weight.max(0) as i64 * 100 + total / 2) / total) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn variant(weight: i32) -> UrlVariant {
+        UrlVariant {
+            id: Uuid::new_v4(),
+            url_id: Uuid::nil(),
+            position: 0,
+            target_url: "https://example.com".to_string(),
+            weight,
+            visit_count: 0,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn shares_follow_the_weights() {
+        let variants = vec![variant(2), variant(1)];
+        assert_eq!(share(&variants, &variants[0]), 67);
+        assert_eq!(share(&variants, &variants[1]), 33);
+    }
+
+    #[test]
+    fn pinned_variant_wins_while_it_exists() {
+        let variants = vec![variant(1), variant(1_000_000)];
+        let id = variants[0].id.to_string();
+        for _ in 0..20 {
+            assert_eq!(pick(&variants, Some(&id)).unwrap().id, variants[0].id);
+        }
+        let gone = Uuid::new_v4().to_string();
+        assert!(pinned(&variants, Some(&gone)).is_none());
+    }
+}
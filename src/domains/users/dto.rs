@@ -18,3 +18,10 @@ pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UserSettings {
+    /// Where visitors of your expired, deleted or used up links are sent
+    #[schema(example = "https://example.com/link-expired")]
+    pub dead_link_url: Option<String>,
+}
@@ -7,6 +7,7 @@ pub struct User {
     pub id: Uuid,
     pub username: String,
     pub password_hash: String,
+    pub dead_link_url: Option<String>,
     pub deleted: bool,
     pub created_at: NaiveDateTime,
 }
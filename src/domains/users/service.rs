@@ -6,14 +6,15 @@ use argon2::{
 };
 use chrono::DateTime;
 use regex::Regex;
+use url::Url;
 
 use crate::{
-    common::errors::AppError,
+    common::{constants, errors::AppError},
     domains::auth::auth::Auth,
     infra::repositories::users::{interface::UserRepository, repository::UsersRepo},
 };
 
-use super::dto::LoginResponse;
+use super::dto::{LoginResponse, UserSettings};
 
 #[derive(Clone)]
 pub struct UserService {
@@ -96,6 +97,49 @@ impl UserService {
         }
     }
 
+    pub async fn get_settings(&self, user_id: &String) -> Result<UserSettings, AppError> {
+        let user = self
+            .repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|_| AppError::NotFound("User not found".to_string()))?;
+        Ok(UserSettings {
+            dead_link_url: user.dead_link_url,
+        })
+    }
+
+    pub async fn update_settings(
+        &self,
+        user_id: &String,
+        settings: &UserSettings,
+    ) -> Result<UserSettings, AppError> {
+        let dead_link_url = match &settings.dead_link_url {
+            Some(url) => {
+                let parsed = Url::parse(url)
+                    .map_err(|_| AppError::ValidationError("URL Error".to_string()))?;
+                if !constants::WEB_SCHEMES.contains(&parsed.scheme()) {
+                    return Err(AppError::ValidationError(
+                        "URL must use http or https".to_string(),
+                    ));
+                }
+                Some(parsed.to_string())
+            }
+            None => None,
+        };
+
+        match self
+            .repo
+            .update_dead_link_url(user_id, &dead_link_url)
+            .await
+        {
+            Ok(user) => Ok(UserSettings {
+                dead_link_url: user.dead_link_url,
+            }),
+            Err(sqlx::Error::RowNotFound) => Err(AppError::NotFound("User not found".to_string())),
+            Err(e) => Err(AppError::DatabaseError(e)),
+        }
+    }
+
     pub fn validate_username(username: &String) -> bool {
         let rgx = Regex::new(r"^[a-zA-Z0-9_.-]{3,32}$").unwrap();
         return rgx.is_match(username);
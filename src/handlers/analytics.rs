@@ -0,0 +1,133 @@
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::{Query, State},
+    http::{
+        HeaderValue, StatusCode,
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE},
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use tokio_stream::StreamExt;
+use utoipa::OpenApi;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+
+use crate::{
+    app_state::AppState,
+    common::{errors::AppError, response::ApiResponse},
+    domains::{
+        analytics::dto::{
+            ClickExport, ClickExportQuery, LinkClicks, OverviewQuery, OverviewResponse,
+        },
+        auth::models::Claims,
+    },
+};
+
+#[utoipa::path(
+    get,
+    path = "/analytics/overview",
+    params(OverviewQuery),
+    responses(
+        (status = 200, description = "Clicks across the user's links, the most clicked and fastest growing links and links without clicks", body = OverviewResponse),
+        (status = 400, description = "Invalid range or time zone"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn get_overview(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<OverviewQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let overview = state
+        .analytics_service
+        .get_overview(&claims.user_id, &query)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, overview))
+}
+
+#[utoipa::path(
+    get,
+    path = "/analytics/clicks/export",
+    params(ClickExportQuery),
+    responses(
+        (status = 200, description = "Raw clicks on all of the user's links, streamed as CSV or NDJSON", content(
+            (String = "text/csv"),
+            (String = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Invalid format, range or time zone"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn export_clicks(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ClickExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let export = state
+        .analytics_service
+        .export_clicks(None, &claims.user_id, &query)
+        .await?;
+    export_response(export)
+}
+
+/// Streams the export as a download. Rows are written as the database
+/// returns them, so the export is never held in memory as a whole.
+pub fn export_response(export: ClickExport) -> Result<Response, AppError> {
+    let format = export.format;
+    let header = tokio_stream::once(Ok(format.header().to_string()));
+    let rows = export
+        .rows
+        .map(move |row| row.map(|click| format.row(&click)));
+
+    let disposition = format!("attachment; filename=\"{}\"", export.filename);
+    let disposition = HeaderValue::from_str(&disposition).map_err(|_| AppError::InternalError)?;
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static(format.content_type()),
+            ),
+            (CONTENT_DISPOSITION, disposition),
+            (CACHE_CONTROL, HeaderValue::from_static("no-store")),
+        ],
+        Body::from_stream(header.chain(rows)),
+    )
+        .into_response())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_overview, export_clicks),
+    components(schemas(OverviewResponse, LinkClicks)),
+    tags(
+        (name = "Analytics", description = "Click reports across all of the user's links")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    modifiers(&AnalyticsApiDoc)
+)]
+pub struct AnalyticsApiDoc;
+
+impl utoipa::Modify for AnalyticsApiDoc {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.as_mut().unwrap();
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        )
+    }
+}
+
+pub fn analytics_routes() -> Router<AppState> {
+    Router::new()
+        .route("/analytics/overview", get(get_overview))
+        .route("/analytics/clicks/export", get(export_clicks))
+}
@@ -2,11 +2,13 @@ use axum::{
     Extension, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get,
 };
 use utoipa::OpenApi;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
 
 use crate::{
     app_state::AppState,
     common::{errors::AppError, response::ApiResponse},
     domains::auth::models::Claims,
+    infra::click_buffer::ClickBufferStats,
 };
 
 #[utoipa::path(
@@ -23,10 +25,30 @@ pub async fn health_check(State(state): State<AppState>) -> Result<impl IntoResp
     //
 }
 
+#[utoipa::path(
+    get,
+    path = "/admin/health/clicks",
+    responses(
+        (status = 200, description = "Click buffer counters, `dropped` grows when clicks arrive faster than they are written", body = ClickBufferStats),
+        (status = 403, description = "caller is not an admin"),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+)]
+#[axum::debug_handler]
+pub async fn click_buffer_stats(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    state.url_service.check_admin(&claims.user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, state.clicks.stats()))
+}
+
 #[derive(OpenApi)]
 #[openapi(
-    paths(health_check),
-    components(),
+    paths(health_check, click_buffer_stats),
+    components(schemas(ClickBufferStats)),
     tags(
         (name = "Health")
     ),
@@ -37,9 +59,22 @@ pub struct HealthApiDoc;
 impl utoipa::Modify for HealthApiDoc {
     fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
         let components = openapi.components.as_mut().unwrap();
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        )
     }
 }
 
 pub fn health_route() -> Router<AppState> {
     Router::new().route("/", get(health_check))
 }
+
+pub fn health_admin_routes() -> Router<AppState> {
+    Router::new().route("/admin/health/clicks", get(click_buffer_stats))
+}
@@ -1,3 +1,6 @@
+pub mod analytics;
 pub mod health;
+pub mod pages;
+pub mod robots;
 pub mod urls;
 pub mod users;
@@ -0,0 +1,203 @@
+use axum::response::Html;
+
+use crate::domains::urls::dto::LinkPreview;
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Quotes a value for use as a JS string literal inside a `<script>` block.
+/// Everything but plain ASCII letters, digits and a few URL characters is
+/// written as a `\u` escape, so the value can't close the string or the tag.
+fn js_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.encode_utf16() {
+        match char::from_u32(c as u32) {
+            Some(ch) if ch.is_ascii_alphanumeric() || "/:.?=_-~%+#,;@!$*()".contains(ch) => {
+                out.push(ch)
+            }
+            _ => out.push_str(&format!("\\u{:04x}", c)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn layout(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{title}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 28rem; margin: 4rem auto; padding: 0 1rem; color: #222; }}
+input, button {{ font-size: 1rem; padding: .5rem; }}
+.error {{ color: #b00020; }}
+</style>
+</head>
+<body>
+{body}
+</body>
+</html>"#,
+        title = escape_html(title),
+        body = body,
+    ))
+}
+
+pub fn password_form(error: Option<&str>, acknowledged: bool) -> Html<String> {
+    let error = error
+        .map(|e| format!(r#"<p class="error">{}</p>"#, escape_html(e)))
+        .unwrap_or_default();
+    // keeps the warning acknowledgement across password attempts
+    let acknowledged = if acknowledged {
+        r#"<input type="hidden" name="acknowledged" value="true">"#
+    } else {
+        ""
+    };
+
+    layout(
+        "Password required",
+        &format!(
+            r#"<h1>Password required</h1>
+<p>This link is protected. Enter the password to continue.</p>
+{error}
+<form method="post">
+{acknowledged}
+<input type="password" name="password" autofocus required>
+<button type="submit">Continue</button>
+</form>"#
+        ),
+    )
+}
+
+pub fn dead_link(gone: bool) -> Html<String> {
+    let (title, message) = if gone {
+        (
+            "Link no longer available",
+            "This link has expired or was removed by its owner.",
+        )
+    } else {
+        (
+            "Link not found",
+            "There is no link at this address. Check it for typos.",
+        )
+    };
+
+    layout(title, &format!("<h1>{}</h1>\n<p>{}</p>", title, message))
+}
+
+pub fn not_yet_active(active_from: &str) -> Html<String> {
+    layout(
+        "Not available yet",
+        &format!(
+            r#"<h1>Not available yet</h1>
+<p>This link becomes available at {} UTC. Please check back later.</p>"#,
+            escape_html(active_from)
+        ),
+    )
+}
+
+pub fn warning(reason: Option<&str>) -> Html<String> {
+    let reason = reason
+        .map(|r| format!(r#"<p class="error">Reason: {}</p>"#, escape_html(r)))
+        .unwrap_or_default();
+
+    layout(
+        "Warning",
+        &format!(
+            r#"<h1>Warning</h1>
+<p>This link has been flagged as potentially harmful. It may lead to phishing, malware or otherwise unsafe content.</p>
+{reason}
+<p>Only continue if you trust where it leads.</p>
+<form method="post">
+<input type="hidden" name="acknowledged" value="true">
+<button type="submit">Continue anyway</button>
+</form>"#
+        ),
+    )
+}
+
+pub fn preview(preview: &LinkPreview, continue_to: &str) -> Html<String> {
+    let owner = preview
+        .owner
+        .as_deref()
+        .map(|owner| format!(" by <strong>{}</strong>", escape_html(owner)))
+        .unwrap_or_default();
+
+    let destinations = match preview.destinations.as_slice() {
+        [] => format!(
+            "<p>This link was created{owner} on {created_at}. It can only be followed a limited number of times, so where it leads is not shown here.</p>",
+            created_at = escape_html(&preview.created_at),
+        ),
+        [only] => format!(
+            "<p>This link was created{owner} on {created_at} and leads to:</p>\n<p><code>{url}</code></p>",
+            created_at = escape_html(&preview.created_at),
+            url = escape_html(&only.url),
+        ),
+        all => {
+            let items: String = all
+                .iter()
+                .map(|destination| {
+                    let share = destination
+                        .share
+                        .map(|share| format!(" ({}% of visits)", share))
+                        .unwrap_or_default();
+                    format!(
+                        "<li><code>{}</code>{}</li>\n",
+                        escape_html(&destination.url),
+                        share
+                    )
+                })
+                .collect();
+            format!(
+                "<p>This link was created{owner} on {created_at} and leads to one of:</p>\n<ul>\n{items}</ul>",
+                created_at = escape_html(&preview.created_at),
+            )
+        }
+    };
+
+    layout(
+        "Link preview",
+        &format!(
+            r#"<h1>Link preview</h1>
+{destinations}
+<p><a href="{continue_to}"><button type="button">Continue</button></a></p>"#,
+            continue_to = escape_html(continue_to),
+        ),
+    )
+}
+
+/// Tries to open the app and falls back to the web url when the page is still
+/// visible after a moment, which means no app handled the scheme.
+pub fn deep_link_bridge(deep_link: &str, fallback: &str) -> Html<String> {
+    layout(
+        "Opening app",
+        &format!(
+            r#"<h1>Opening app</h1>
+<p>If nothing happens, <a href="{fallback_href}">continue in the browser</a>.</p>
+<script>
+(function () {{
+  var fallback = setTimeout(function () {{
+    window.location.replace({fallback_js});
+  }}, 1500);
+  document.addEventListener("visibilitychange", function () {{
+    if (document.hidden) {{ clearTimeout(fallback); }}
+  }});
+  window.location.href = {deep_link_js};
+}})();
+</script>
+<noscript><meta http-equiv="refresh" content="0; url={fallback_href}"></noscript>"#,
+            fallback_href = escape_html(fallback),
+            fallback_js = js_string(fallback),
+            deep_link_js = js_string(deep_link),
+        ),
+    )
+}
@@ -0,0 +1,24 @@
+use axum::{
+    Router,
+    extract::State,
+    http::{HeaderValue, header::CONTENT_TYPE},
+    response::IntoResponse,
+    routing::get,
+};
+
+use crate::app_state::AppState;
+
+#[axum::debug_handler]
+pub async fn robots_txt(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        )],
+        state.robots_txt.as_str().to_string(),
+    )
+}
+
+pub fn robots_routes() -> Router<AppState> {
+    Router::new().route("/robots.txt", get(robots_txt))
+}
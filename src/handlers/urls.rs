@@ -1,20 +1,35 @@
 use crate::common::response::ApiResponse;
+use crate::common::{client_ip, constants};
+use crate::domains::analytics::dto::{
+    ClickExportQuery, StatsCount, StatsPoint, UrlStatsQuery, UrlStatsResponse,
+};
 use crate::domains::auth::models::Claims;
-use crate::domains::urls::dto::{FavouriteUrl, UrlRequest, UrlResponse};
+use crate::domains::urls::dto::{
+    DeadLinkFallback, FavouriteUrl, Landing, UnlockUrl, UrlRequest, UrlResponse, UrlRuleRequest,
+    UrlRuleResponse, UrlVariantRequest, UrlVariantResponse, UrlVariantsRequest,
+    UrlVariantsResponse, UtmPresetRequest, UtmPresetResponse, Visit, VisitContext, VisitPath,
+    WarnUrl,
+};
+use crate::domains::urls::models::{RuleConditions, UtmParams};
+use crate::handlers::{analytics, pages};
 use crate::{app_state::AppState, common::errors::AppError};
 use axum::Router;
-use axum::http::StatusCode;
-use axum::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
-use axum::response::Redirect;
+use axum::http::header::{
+    ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, COOKIE, LOCATION, REFERER,
+    SET_COOKIE, USER_AGENT,
+};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{Html, Response};
 use axum::{
-    Extension, Json,
-    extract::{Path, State},
+    Extension, Form, Json,
+    extract::{ConnectInfo, Path, Query, RawQuery, State},
     response::IntoResponse,
     routing::delete,
     routing::get,
     routing::patch,
     routing::post,
 };
+use std::net::SocketAddr;
 use utoipa::OpenApi;
 use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
 use uuid::Uuid;
@@ -25,6 +40,8 @@ use uuid::Uuid;
     request_body = UrlRequest,
     responses(
         (status = 200, description = "URL shortened successfully", body = UrlResponse),
+        (status = 400, description = "Invalid URL, alias or expiry"),
+        (status = 409, description = "Alias already taken"),
     ),
 )]
 #[axum::debug_handler]
@@ -37,7 +54,7 @@ pub async fn shorten_url(
     println!("Claims: {:?}", claims);
     let url = state
         .url_service
-        .shorten_url(&payload.url, &claims.user_id)
+        .shorten_url(&payload, &claims.user_id)
         .await?;
     Ok(ApiResponse::success(StatusCode::OK, url))
     //
@@ -59,17 +76,285 @@ pub async fn delete_url(
 }
 
 #[utoipa::path(
-    get,
+    method(get, head),
     path = "/{code}",
-    responses((status = 302, description = "url redirect")),
+    description = "HEAD answers like GET without counting the visit. Bots and prefetches are followed but not counted either. On click-limited links visits that aren't counted get a 204 without the destination. Redirects carry an `X-Robots-Tag` header telling crawlers whether the link may be indexed.",
+    responses(
+        (status = 301, description = "permanent url redirect"),
+        (status = 302, description = "url redirect"),
+        (status = 307, description = "temporary url redirect"),
+        (status = 308, description = "permanent url redirect"),
+        (status = 200, description = "password form, warning, not-yet-active placeholder, deep link bridge for mobile visitors or, for `/{code}+`, a preview page", content_type = "text/html"),
+        (status = 204, description = "HEAD request, bot or prefetch on a click-limited link, answered without the destination"),
+        (status = 404, description = "url not found or not active yet, unless a fallback url is configured", content_type = "text/html"),
+        (status = 410, description = "url deleted, expired or click limit reached, unless a fallback url is configured", content_type = "text/html"),
+    ),
 )]
 #[axum::debug_handler]
 pub async fn enter_url(
     State(state): State<AppState>,
-    Path(code): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    method: Method,
+    Path(path): Path<VisitPath>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let (code, mut ctx) = visit_context(&state, peer, path, query, &headers);
+    // link checkers and crawlers probe with HEAD, answer like GET but don't count it
+    ctx.dry_run = method == Method::HEAD;
+    match state.url_service.enter_url(&code, &ctx).await? {
+        Visit::Redirect {
+            landing,
+            redirect_type,
+        } => landing_response(&code, &landing, redirect_type),
+        Visit::PasswordRequired => Ok(page_response(
+            StatusCode::OK,
+            pages::password_form(None, false),
+        )),
+        Visit::Warning(reason) => Ok(page_response(
+            StatusCode::OK,
+            pages::warning(reason.as_deref()),
+        )),
+        Visit::NotYetActive(active_from) => Ok(page_response(
+            StatusCode::OK,
+            pages::not_yet_active(&active_from),
+        )),
+        Visit::DeepLink { deep_link, landing } => bridge_response(&code, &deep_link, &landing),
+        Visit::DeadLink { gone, fallback } => dead_link_response(gone, fallback),
+        Visit::Withheld => Ok(withheld_response()),
+        Visit::Preview(preview) => {
+            let mut continue_to = format!("/{}", code);
+            if let Some(rest) = &ctx.extra_path {
+                continue_to = format!("{}/{}", continue_to, rest);
+            }
+            if let Some(query) = &ctx.query {
+                continue_to = format!("{}?{}", continue_to, query);
+            }
+            Ok(page_response(
+                StatusCode::OK,
+                pages::preview(&preview, &continue_to),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/{code}",
+    request_body(content = UnlockUrl, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "password accepted or warning acknowledged, url redirect"),
+        (status = 200, description = "warning or password form", content_type = "text/html"),
+        (status = 401, description = "wrong password", content_type = "text/html"),
+        (status = 429, description = "too many failed attempts", content_type = "text/html"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn unlock_url(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(path): Path<VisitPath>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    Form(payload): Form<UnlockUrl>,
 ) -> Result<impl IntoResponse, AppError> {
-    let url = state.url_service.enter_url(&code).await?;
-    Ok(Redirect::permanent(url.url.as_str()))
+    let (code, ctx) = visit_context(&state, peer, path, query, &headers);
+    let acknowledged = payload.acknowledged;
+    match state.url_service.unlock_url(&code, &payload, &ctx).await {
+        // 303 so the browser follows with a GET instead of replaying the form post
+        Ok(Visit::Redirect { landing, .. }) => {
+            landing_response(&code, &landing, StatusCode::SEE_OTHER.as_u16())
+        }
+        Ok(Visit::DeepLink { deep_link, landing }) => bridge_response(&code, &deep_link, &landing),
+        Ok(Visit::DeadLink { gone, fallback }) => dead_link_response(gone, fallback),
+        Ok(Visit::Withheld) => Ok(withheld_response()),
+        Ok(Visit::Warning(reason)) => Ok(page_response(
+            StatusCode::OK,
+            pages::warning(reason.as_deref()),
+        )),
+        Ok(_) => Ok(page_response(
+            StatusCode::OK,
+            pages::password_form(None, acknowledged),
+        )),
+        Err(AppError::ValidationError(msg)) => Ok(page_response(
+            StatusCode::UNAUTHORIZED,
+            pages::password_form(Some(&msg), acknowledged),
+        )),
+        Err(AppError::TooManyRequests(msg)) => Ok(page_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            pages::password_form(Some(&msg), acknowledged),
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Splits the short code from the request, a trailing `+` asks for the
+/// preview page.
+fn visit_context(
+    state: &AppState,
+    peer: SocketAddr,
+    path: VisitPath,
+    query: Option<String>,
+    headers: &HeaderMap,
+) -> (String, VisitContext) {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let (code, preview) = match path.code.strip_suffix('+') {
+        Some(code) => (code.to_string(), true),
+        None => (path.code, false),
+    };
+    let ctx = VisitContext {
+        extra_path: path.rest,
+        query,
+        user_agent: header(USER_AGENT),
+        accept_language: header(ACCEPT_LANGUAGE),
+        referrer: header(REFERER),
+        purpose: constants::PURPOSE_HEADERS
+            .iter()
+            .find_map(|name| headers.get(*name)?.to_str().ok())
+            .map(|v| v.to_string()),
+        client_ip: Some(client_ip::client_ip(
+            peer.ip(),
+            headers,
+            &state.trusted_proxies,
+        )),
+        variant: cookie(headers, constants::VARIANT_COOKIE),
+        preview,
+        dry_run: false,
+    };
+    (code, ctx)
+}
+
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Redirects to the landing and pins the visitor to its variant. The cookie
+/// is scoped to the link's path so every link keeps its own variant.
+fn landing_response(
+    code: &str,
+    landing: &Landing,
+    redirect_type: u16,
+) -> Result<Response, AppError> {
+    let response = redirect_response(&landing.location, redirect_type)?;
+    with_variant_cookie(with_robots_tag(response, landing), code, landing)
+}
+
+fn bridge_response(code: &str, deep_link: &str, landing: &Landing) -> Result<Response, AppError> {
+    let response = page_response(
+        StatusCode::OK,
+        pages::deep_link_bridge(deep_link, &landing.location),
+    );
+    with_variant_cookie(with_robots_tag(response, landing), code, landing)
+}
+
+fn with_robots_tag(mut response: Response, landing: &Landing) -> Response {
+    let tag = if landing.indexable {
+        constants::ROBOTS_TAG_ALLOW
+    } else {
+        constants::ROBOTS_TAG_DENY
+    };
+    response
+        .headers_mut()
+        .insert(constants::X_ROBOTS_TAG, HeaderValue::from_static(tag));
+    response
+}
+
+fn with_variant_cookie(
+    mut response: Response,
+    code: &str,
+    landing: &Landing,
+) -> Result<Response, AppError> {
+    if let Some(variant) = &landing.sticky_variant {
+        let cookie = format!(
+            "{}={}; Path=/{}; Max-Age={}; HttpOnly; SameSite=Lax",
+            constants::VARIANT_COOKIE,
+            variant,
+            code,
+            constants::VARIANT_COOKIE_MAX_AGE
+        );
+        let cookie = HeaderValue::from_str(&cookie).map_err(|_| AppError::InternalError)?;
+        response.headers_mut().insert(SET_COOKIE, cookie);
+    }
+    Ok(response)
+}
+
+fn dead_link_response(
+    gone: bool,
+    fallback: Option<DeadLinkFallback>,
+) -> Result<Response, AppError> {
+    let status = if gone {
+        StatusCode::GONE
+    } else {
+        StatusCode::NOT_FOUND
+    };
+    match fallback {
+        Some(DeadLinkFallback::Redirect(location)) => {
+            redirect_response(&location, StatusCode::FOUND.as_u16())
+        }
+        Some(DeadLinkFallback::Page(page)) => Ok(page_response(status, Html(page))),
+        None => Ok(page_response(status, pages::dead_link(gone))),
+    }
+}
+
+/// No destination, so that probing a click-limited link doesn't reveal it.
+fn withheld_response() -> Response {
+    let mut response = (
+        StatusCode::NO_CONTENT,
+        [(
+            CACHE_CONTROL,
+            HeaderValue::from_static(constants::TEMPORARY_CACHE_CONTROL),
+        )],
+    )
+        .into_response();
+    response.headers_mut().insert(
+        constants::X_ROBOTS_TAG,
+        HeaderValue::from_static(constants::ROBOTS_TAG_DENY),
+    );
+    response
+}
+
+fn page_response(status: StatusCode, page: Html<String>) -> Response {
+    (
+        status,
+        [(
+            CACHE_CONTROL,
+            HeaderValue::from_static(constants::TEMPORARY_CACHE_CONTROL),
+        )],
+        page,
+    )
+        .into_response()
+}
+
+fn redirect_response(location: &str, redirect_type: u16) -> Result<Response, AppError> {
+    let status = StatusCode::from_u16(redirect_type).unwrap_or(StatusCode::FOUND);
+    let cache_control = match status {
+        StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT => {
+            constants::PERMANENT_CACHE_CONTROL
+        }
+        _ => constants::TEMPORARY_CACHE_CONTROL,
+    };
+    let location = HeaderValue::from_str(location).map_err(|_| AppError::InternalError)?;
+
+    Ok((
+        status,
+        [
+            (LOCATION, location),
+            (CACHE_CONTROL, HeaderValue::from_static(cache_control)),
+        ],
+    )
+        .into_response())
 }
 
 #[utoipa::path(
@@ -91,6 +376,30 @@ pub async fn favourite_url(
     Ok(ApiResponse::success(StatusCode::OK, url))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/admin/urls/warn/{id}",
+    request_body = WarnUrl,
+    responses(
+        (status = 200, description = "warning flag set or cleared", body = UrlResponse),
+        (status = 403, description = "caller is not an admin"),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn set_url_warning(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<WarnUrl>,
+) -> Result<impl IntoResponse, AppError> {
+    let url = state
+        .url_service
+        .set_url_warning(&id, &payload, &claims.user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, url))
+}
+
 #[utoipa::path(
     get,
     path = "/urls/user",
@@ -105,10 +414,287 @@ pub async fn get_user_urls(
     Ok(ApiResponse::success(StatusCode::OK, urls))
 }
 
+#[utoipa::path(
+    post,
+    path = "/urls/utm-presets",
+    request_body = UtmPresetRequest,
+    responses(
+        (status = 200, description = "UTM preset created", body = UtmPresetResponse),
+        (status = 409, description = "Preset name already used"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn create_utm_preset(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UtmPresetRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let preset = state
+        .url_service
+        .create_utm_preset(&payload, &claims.user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, preset))
+}
+
+#[utoipa::path(
+    get,
+    path = "/urls/utm-presets",
+    responses((status = 200, description = "user UTM presets", body = Vec<UtmPresetResponse>)),
+)]
+#[axum::debug_handler]
+pub async fn get_utm_presets(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    let presets = state.url_service.get_utm_presets(&claims.user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, presets))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/urls/utm-presets/{id}",
+    responses((status = 200, description = "UTM preset deleted", body = UtmPresetResponse)),
+)]
+#[axum::debug_handler]
+pub async fn delete_utm_preset(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let preset = state
+        .url_service
+        .delete_utm_preset(&id, &claims.user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, preset))
+}
+
+#[utoipa::path(
+    get,
+    path = "/urls/{id}/rules",
+    responses(
+        (status = 200, description = "routing rules in evaluation order", body = Vec<UrlRuleResponse>),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn get_url_rules(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let rules = state
+        .url_service
+        .get_url_rules(&id, &claims.user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, rules))
+}
+
+#[utoipa::path(
+    post,
+    path = "/urls/{id}/rules",
+    request_body = UrlRuleRequest,
+    responses(
+        (status = 200, description = "rule appended after the existing ones", body = UrlRuleResponse),
+        (status = 400, description = "Invalid target URL or no condition"),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn add_url_rule(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<UrlRuleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let rule = state
+        .url_service
+        .add_url_rule(&id, &claims.user_id, &payload)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, rule))
+}
+
+#[utoipa::path(
+    put,
+    path = "/urls/{id}/rules",
+    request_body = Vec<UrlRuleRequest>,
+    responses(
+        (status = 200, description = "rules replaced, in the given order", body = Vec<UrlRuleResponse>),
+        (status = 400, description = "Invalid target URL or no condition"),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn replace_url_rules(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<Vec<UrlRuleRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let rules = state
+        .url_service
+        .replace_url_rules(&id, &claims.user_id, &payload)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, rules))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/urls/{id}/rules/{rule_id}",
+    responses(
+        (status = 200, description = "rule deleted", body = UrlRuleResponse),
+        (status = 404, description = "URL or rule not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn delete_url_rule(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, rule_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    let rule = state
+        .url_service
+        .delete_url_rule(&id, &rule_id, &claims.user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, rule))
+}
+
+#[utoipa::path(
+    get,
+    path = "/urls/{id}/stats",
+    params(UrlStatsQuery),
+    responses(
+        (status = 200, description = "Clicks, unique visitors and top referrers, countries and devices for the range", body = UrlStatsResponse),
+        (status = 400, description = "Invalid range, granularity or time zone"),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn get_url_stats(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Query(query): Query<UrlStatsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let stats = state
+        .analytics_service
+        .get_url_stats(&id, &claims.user_id, &query)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/urls/{id}/clicks/export",
+    params(ClickExportQuery),
+    responses(
+        (status = 200, description = "Raw clicks on the link, streamed as CSV or NDJSON", content(
+            (String = "text/csv"),
+            (String = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Invalid format, range or time zone"),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn export_url_clicks(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Query(query): Query<ClickExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let export = state
+        .analytics_service
+        .export_clicks(Some(&id), &claims.user_id, &query)
+        .await?;
+    analytics::export_response(export)
+}
+
+#[utoipa::path(
+    get,
+    path = "/urls/{id}/variants",
+    responses(
+        (status = 200, description = "A/B variants with their visit counts", body = UrlVariantsResponse),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn get_url_variants(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let variants = state
+        .url_service
+        .get_url_variants(&id, &claims.user_id)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, variants))
+}
+
+#[utoipa::path(
+    put,
+    path = "/urls/{id}/variants",
+    request_body = UrlVariantsRequest,
+    responses(
+        (status = 200, description = "variants replaced, an empty list turns rotation off", body = UrlVariantsResponse),
+        (status = 400, description = "Invalid variant URL, weight or count"),
+        (status = 404, description = "URL not found"),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn replace_url_variants(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<UrlVariantsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let variants = state
+        .url_service
+        .replace_url_variants(&id, &claims.user_id, &payload)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, variants))
+}
+
 #[derive(OpenApi)]
 #[openapi(
-    paths(shorten_url, delete_url, enter_url, favourite_url, get_user_urls),
-    components(schemas(UrlRequest, UrlResponse)),
+    paths(
+        shorten_url,
+        delete_url,
+        enter_url,
+        unlock_url,
+        favourite_url,
+        set_url_warning,
+        get_user_urls,
+        create_utm_preset,
+        get_utm_presets,
+        delete_utm_preset,
+        get_url_rules,
+        add_url_rule,
+        replace_url_rules,
+        delete_url_rule,
+        get_url_variants,
+        replace_url_variants,
+        get_url_stats,
+        export_url_clicks
+    ),
+    components(schemas(
+        UrlRequest,
+        UrlResponse,
+        UnlockUrl,
+        WarnUrl,
+        UtmParams,
+        UtmPresetRequest,
+        UtmPresetResponse,
+        RuleConditions,
+        UrlRuleRequest,
+        UrlRuleResponse,
+        UrlVariantRequest,
+        UrlVariantsRequest,
+        UrlVariantResponse,
+        UrlVariantsResponse,
+        UrlStatsResponse,
+        StatsPoint,
+        StatsCount
+    )),
     tags(
         (name = "URLs", description = "Operations related to URL shortening")
     ),
@@ -140,6 +726,7 @@ pub fn url_routes() -> Router<AppState> {
         .allow_methods([
             axum::http::Method::GET,
             axum::http::Method::POST,
+            axum::http::Method::PUT,
             axum::http::Method::DELETE,
             axum::http::Method::OPTIONS,
         ])
@@ -148,8 +735,33 @@ pub fn url_routes() -> Router<AppState> {
     Router::new()
         .route("/urls/shorten", post(shorten_url))
         .route("/urls/delete/{id}", delete(delete_url))
-        .route("/{code}", get(enter_url))
         .route("/urls/favourite/{id}", patch(favourite_url))
+        .route("/admin/urls/warn/{id}", patch(set_url_warning))
         .route("/urls/user", get(get_user_urls))
+        .route(
+            "/urls/utm-presets",
+            post(create_utm_preset).get(get_utm_presets),
+        )
+        .route("/urls/utm-presets/{id}", delete(delete_utm_preset))
+        .route(
+            "/urls/{id}/rules",
+            get(get_url_rules).post(add_url_rule).put(replace_url_rules),
+        )
+        .route("/urls/{id}/rules/{rule_id}", delete(delete_url_rule))
+        .route(
+            "/urls/{id}/variants",
+            get(get_url_variants).put(replace_url_variants),
+        )
+        .route("/urls/{id}/stats", get(get_url_stats))
+        .route("/urls/{id}/clicks/export", get(export_url_clicks))
         .layer(cors)
 }
+
+pub fn redirect_routes() -> Router<AppState> {
+    Router::new()
+        .route("/{code}", get(enter_url).head(enter_url).post(unlock_url))
+        .route(
+            "/{code}/{*rest}",
+            get(enter_url).head(enter_url).post(unlock_url),
+        )
+}
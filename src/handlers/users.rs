@@ -1,10 +1,19 @@
-use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::post};
+use axum::{
+    Extension, Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+};
 use utoipa::OpenApi;
 
 use crate::{
     app_state::AppState,
     common::{errors::AppError, response::ApiResponse},
-    domains::users::dto::{LoginRequest, LoginResponse, Register},
+    domains::{
+        auth::models::Claims,
+        users::dto::{LoginRequest, LoginResponse, Register, UserSettings},
+    },
 };
 
 #[axum::debug_handler]
@@ -45,10 +54,48 @@ pub async fn register(
     Ok(ApiResponse::success(StatusCode::OK, user)) //empty
 }
 
+#[axum::debug_handler]
+#[utoipa::path(
+    get,
+    path = "/users/settings",
+    responses((status = 200, description = "user settings", body = UserSettings)),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_settings(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    let settings = state.user_service.get_settings(&claims.user_id).await?;
+    Ok(ApiResponse::success(StatusCode::OK, settings))
+}
+
+#[axum::debug_handler]
+#[utoipa::path(
+    put,
+    path = "/users/settings",
+    request_body = UserSettings,
+    responses(
+        (status = 200, description = "user settings updated", body = UserSettings),
+        (status = 400, description = "Invalid fallback URL"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn update_settings(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UserSettings>,
+) -> Result<impl IntoResponse, AppError> {
+    let settings = state
+        .user_service
+        .update_settings(&claims.user_id, &payload)
+        .await?;
+    Ok(ApiResponse::success(StatusCode::OK, settings))
+}
+
 #[derive(OpenApi)]
 #[openapi(
-    paths(register, login),
-    components(schemas(Register, LoginResponse, LoginRequest)),
+    paths(register, login, get_settings, update_settings),
+    components(schemas(Register, LoginResponse, LoginRequest, UserSettings)),
     tags(
         (name = "Users", description = "Operations related to users")
     ),
@@ -67,3 +114,7 @@ pub fn user_routes() -> Router<AppState> {
         .route("/register", post(register))
         .route("/login", post(login))
 }
+
+pub fn user_settings_routes() -> Router<AppState> {
+    Router::new().route("/users/settings", get(get_settings).put(update_settings))
+}
@@ -0,0 +1,186 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::{
+    sync::{Notify, mpsc},
+    task::JoinHandle,
+};
+use tracing::{error, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    domains::analytics::models::NewClick, infra::repositories::clicks::interface::ClickRepository,
+};
+
+#[derive(Default)]
+struct Counters {
+    accepted: AtomicU64,
+    dropped: AtomicU64,
+    written: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClickBufferStats {
+    /// Clicks accepted into the buffer since startup
+    pub accepted: u64,
+    /// Clicks thrown away because the buffer was full
+    pub dropped: u64,
+    /// Clicks written to the database
+    pub written: u64,
+    /// Clicks lost because their batch failed to insert
+    pub failed: u64,
+    /// Clicks waiting to be written
+    pub queued: u64,
+}
+
+/// Keeps click writes off the redirect path. Clicks go into a bounded channel
+/// and a background task inserts them in batches, either when a batch is full
+/// or when the flush interval passes. When the channel is full new clicks are
+/// dropped rather than slowing redirects down.
+#[derive(Clone)]
+pub struct ClickBuffer {
+    sender: mpsc::Sender<NewClick>,
+    counters: Arc<Counters>,
+    shutdown: Arc<Notify>,
+    flusher: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl ClickBuffer {
+    pub fn start(
+        click_repo: Arc<dyn ClickRepository + Send + Sync>,
+        capacity: usize,
+        batch_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let counters = Arc::new(Counters::default());
+        let shutdown = Arc::new(Notify::new());
+
+        let flusher = tokio::spawn(Self::run(
+            click_repo,
+            receiver,
+            Arc::clone(&counters),
+            Arc::clone(&shutdown),
+            batch_size.max(1),
+            flush_interval.max(Duration::from_millis(10)),
+        ));
+
+        ClickBuffer {
+            sender,
+            counters,
+            shutdown,
+            flusher: Arc::new(Mutex::new(Some(flusher))),
+        }
+    }
+
+    /// Queues the click without waiting.
+    pub fn record(&self, click: NewClick) {
+        match self.sender.try_send(click) {
+            Ok(()) => {
+                self.counters.accepted.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> ClickBufferStats {
+        let accepted = self.counters.accepted.load(Ordering::Relaxed);
+        let written = self.counters.written.load(Ordering::Relaxed);
+        let failed = self.counters.failed.load(Ordering::Relaxed);
+        ClickBufferStats {
+            accepted,
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            written,
+            failed,
+            queued: accepted.saturating_sub(written + failed),
+        }
+    }
+
+    /// Stops accepting clicks and waits until everything queued is written.
+    pub async fn shutdown(&self) {
+        let flusher = self.flusher.lock().unwrap().take();
+        let Some(flusher) = flusher else {
+            return;
+        };
+        self.shutdown.notify_one();
+        if let Err(e) = flusher.await {
+            error!("Click flusher stopped with an error: {}", e);
+        }
+    }
+
+    async fn run(
+        click_repo: Arc<dyn ClickRepository + Send + Sync>,
+        mut receiver: mpsc::Receiver<NewClick>,
+        counters: Arc<Counters>,
+        shutdown: Arc<Notify>,
+        batch_size: usize,
+        flush_interval: Duration,
+    ) {
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut ticker = tokio::time::interval(flush_interval);
+        let mut reported_drops = 0;
+        loop {
+            tokio::select! {
+                received = receiver.recv() => match received {
+                    Some(click) => {
+                        batch.push(click);
+                        if batch.len() >= batch_size {
+                            Self::flush(click_repo.as_ref(), &mut batch, &counters).await;
+                        }
+                    }
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    Self::flush(click_repo.as_ref(), &mut batch, &counters).await;
+                    let dropped = counters.dropped.load(Ordering::Relaxed);
+                    if dropped > reported_drops {
+                        warn!("Click buffer full, dropped {} clicks", dropped - reported_drops);
+                        reported_drops = dropped;
+                    }
+                }
+                _ = shutdown.notified() => {
+                    // no new clicks from here on, drain what is already queued
+                    receiver.close();
+                    while let Some(click) = receiver.recv().await {
+                        batch.push(click);
+                        if batch.len() >= batch_size {
+                            Self::flush(click_repo.as_ref(), &mut batch, &counters).await;
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+        Self::flush(click_repo.as_ref(), &mut batch, &counters).await;
+    }
+
+    async fn flush(
+        click_repo: &(dyn ClickRepository + Send + Sync),
+        batch: &mut Vec<NewClick>,
+        counters: &Counters,
+    ) {
+        if batch.is_empty() {
+            return;
+        }
+        let count = batch.len() as u64;
+        match click_repo.create_many(batch).await {
+            Ok(()) => {
+                counters.written.fetch_add(count, Ordering::Relaxed);
+            }
+            Err(e) => {
+                counters.failed.fetch_add(count, Ordering::Relaxed);
+                error!("Failed to write {} clicks: {}", count, e);
+            }
+        }
+        batch.clear();
+    }
+}
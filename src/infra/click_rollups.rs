@@ -0,0 +1,73 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{TimeDelta, Utc};
+use tracing::{error, info};
+
+use crate::infra::repositories::clicks::interface::ClickRepository;
+
+#[derive(Debug, Clone)]
+pub struct ClickRollupReport {
+    /// Raw clicks added to the rollups
+    pub rolled_up: u64,
+    /// Raw clicks deleted after the retention period
+    pub pruned: u64,
+}
+
+/// Rolls raw clicks up into 15 minute and daily per link counts and then
+/// deletes raw clicks older than the retention period. Only clicks that are
+/// already rolled up are deleted, so stats stay the same after pruning.
+pub struct ClickRollups {
+    click_repo: Arc<dyn ClickRepository + Send + Sync>,
+    retention: Option<TimeDelta>,
+}
+
+impl ClickRollups {
+    /// A retention of 0 days keeps raw clicks forever.
+    pub fn new(click_repo: Arc<dyn ClickRepository + Send + Sync>, retention_days: u32) -> Self {
+        ClickRollups {
+            click_repo,
+            retention: (retention_days > 0).then(|| TimeDelta::days(retention_days.into())),
+        }
+    }
+
+    pub async fn run(&self) -> Result<ClickRollupReport, sqlx::Error> {
+        let rolled_up = self.click_repo.roll_up().await?;
+
+        let pruned = match self.retention {
+            Some(retention) => {
+                let before = Utc::now().naive_utc() - retention;
+                self.click_repo.prune_clicks(&before).await?
+            }
+            None => 0,
+        };
+        Ok(ClickRollupReport { rolled_up, pruned })
+    }
+
+    /// Runs the rollup in the background every `interval`, a zero interval
+    /// leaves it to the `rollup` command.
+    pub fn schedule(self: Arc<Self>, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.run().await {
+                    Ok(report) => {
+                        if report.rolled_up > 0 || report.pruned > 0 {
+                            info!(
+                                "Rolled up {} clicks, pruned {} raw clicks",
+                                report.rolled_up, report.pruned
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to roll up clicks: {}", e);
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fs,
+    net::IpAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use maxminddb::{MaxMindDBError, Reader, geoip2};
+use tracing::{error, info, warn};
+
+/// The loaded database and the modification time of the file it came from.
+type Loaded = (Arc<Reader<Vec<u8>>>, SystemTime);
+
+/// Country lookups against a local `.mmdb` file. The file is re-read when its
+/// modification time changes, so it can be swapped without a restart.
+pub struct GeoIp {
+    path: Option<String>,
+    db: RwLock<Option<Loaded>>,
+}
+
+impl GeoIp {
+    pub fn new(path: Option<String>) -> Self {
+        let geoip = GeoIp {
+            path,
+            db: RwLock::new(None),
+        };
+        geoip.reload();
+        geoip
+    }
+
+    /// ISO 3166-1 alpha-2 code of the country the address is located in.
+    pub fn country(&self, ip: IpAddr) -> Option<String> {
+        let reader = self.db.read().unwrap().as_ref()?.0.clone();
+        let record: geoip2::Country = match reader.lookup(ip) {
+            Ok(record) => record,
+            Err(MaxMindDBError::AddressNotFoundError(_)) => return None,
+            Err(e) => {
+                warn!("GeoIP lookup for {} failed: {}", ip, e);
+                return None;
+            }
+        };
+
+        [record.country, record.registered_country]
+            .into_iter()
+            .find_map(|country| country?.iso_code)
+            .map(|code| code.to_uppercase())
+    }
+
+    /// Loads the file if it changed since the last load. A file that can't be
+    /// read or parsed keeps the previous database in place.
+    pub fn reload(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let modified = match fs::metadata(path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(e) => {
+                warn!("GeoIP database {} not readable: {}", path, e);
+                return;
+            }
+        };
+        let loaded = self.db.read().unwrap().as_ref().map(|(_, at)| *at);
+        if loaded == Some(modified) {
+            return;
+        }
+
+        match fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| Reader::from_source(bytes).map_err(|e| e.to_string()))
+        {
+            Ok(reader) => {
+                *self.db.write().unwrap() = Some((Arc::new(reader), modified));
+                info!("GeoIP database loaded from {}", path);
+            }
+            Err(e) => error!("GeoIP database {} not loaded: {}", path, e),
+        }
+    }
+
+    pub fn watch(self: Arc<Self>, interval: Duration) {
+        if self.path.is_none() || interval.is_zero() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let geoip = Arc::clone(&self);
+                // reading a large database shouldn't block a runtime worker
+                let _ = tokio::task::spawn_blocking(move || geoip.reload()).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, net::Ipv4Addr, path::PathBuf};
+
+    use super::*;
+
+    const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+    const DATA_SECTION_SEPARATOR: usize = 16;
+
+    fn control(kind: u8, size: usize) -> Vec<u8> {
+        assert!(size < 29);
+        if kind <= 7 {
+            vec![(kind << 5) | size as u8]
+        } else {
+            vec![size as u8, kind - 7]
+        }
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        [control(2, s.len()), s.as_bytes().to_vec()].concat()
+    }
+
+    fn uint(kind: u8, n: u64) -> Vec<u8> {
+        let bytes: Vec<u8> = n
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        [control(kind, bytes.len()), bytes].concat()
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut out = control(7, entries.len());
+        for (key, value) in entries {
+            out.extend(string(key));
+            out.extend(value);
+        }
+        out
+    }
+
+    fn country(key: &str, code: &str) -> Vec<u8> {
+        map(&[(key, map(&[("iso_code", string(code))]))])
+    }
+
+    /// A database with a single IPv4 node: addresses starting with a 0 bit
+    /// (0.0.0.0/1) point at `data`, the rest have no record.
+    fn database(record_size: usize, data: Vec<u8>) -> Vec<u8> {
+        let node_count = 1;
+        let left = node_count + DATA_SECTION_SEPARATOR;
+        let right = node_count;
+        let node = match record_size {
+            24 => [&left.to_be_bytes()[5..], &right.to_be_bytes()[5..]].concat(),
+            28 => {
+                let high = (((left >> 24) & 0xF) << 4 | ((right >> 24) & 0xF)) as u8;
+                [&left.to_be_bytes()[5..], &[high], &right.to_be_bytes()[5..]].concat()
+            }
+            _ => [&left.to_be_bytes()[4..], &right.to_be_bytes()[4..]].concat(),
+        };
+
+        let metadata = map(&[
+            ("binary_format_major_version", uint(5, 2)),
+            ("binary_format_minor_version", uint(5, 0)),
+            ("build_epoch", uint(9, 1_700_000_000)),
+            ("database_type", string("GeoIP2-Country")),
+            ("description", map(&[])),
+            ("ip_version", uint(5, 4)),
+            ("languages", control(11, 0)),
+            ("node_count", uint(6, node_count as u64)),
+            ("record_size", uint(5, record_size as u64)),
+        ]);
+        [
+            node,
+            vec![0; DATA_SECTION_SEPARATOR],
+            data,
+            METADATA_MARKER.to_vec(),
+            metadata,
+        ]
+        .concat()
+    }
+
+    fn write(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "url-shortener-geoip-{}-{}.mmdb",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn geoip(name: &str, contents: &[u8]) -> GeoIp {
+        GeoIp::new(Some(write(name, contents).to_string_lossy().into_owned()))
+    }
+
+    fn ip(octets: [u8; 4]) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::from(octets))
+    }
+
+    #[test]
+    fn finds_the_country_for_every_record_size() {
+        for record_size in [24, 28, 32] {
+            let db = database(record_size, country("country", "de"));
+            let geoip = geoip(&format!("record-size-{record_size}"), &db);
+            assert_eq!(
+                geoip.country(ip([10, 0, 0, 1])).as_deref(),
+                Some("DE"),
+                "record size {record_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_registered_country() {
+        let geoip = geoip(
+            "registered",
+            &database(24, country("registered_country", "fr")),
+        );
+        assert_eq!(geoip.country(ip([10, 0, 0, 1])).as_deref(), Some("FR"));
+    }
+
+    #[test]
+    fn address_without_a_record_has_no_country() {
+        let geoip = geoip("missing", &database(24, country("country", "de")));
+        assert_eq!(geoip.country(ip([200, 0, 0, 1])), None);
+    }
+
+    #[test]
+    fn without_a_database_there_is_no_country() {
+        assert_eq!(GeoIp::new(None).country(ip([10, 0, 0, 1])), None);
+        let geoip = geoip("corrupt", b"not a database");
+        assert_eq!(geoip.country(ip([10, 0, 0, 1])), None);
+    }
+
+    #[test]
+    fn keeps_the_loaded_database_when_the_file_is_replaced_by_a_broken_one() {
+        let path = write("replaced", &database(24, country("country", "de")));
+        let geoip = GeoIp::new(Some(path.to_string_lossy().into_owned()));
+
+        fs::write(&path, b"truncated").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        geoip.reload();
+
+        assert_eq!(geoip.country(ip([10, 0, 0, 1])).as_deref(), Some("DE"));
+    }
+}
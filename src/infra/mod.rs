@@ -1,2 +1,5 @@
+pub mod click_buffer;
+pub mod click_rollups;
 pub mod db;
+pub mod geoip;
 pub mod repositories;
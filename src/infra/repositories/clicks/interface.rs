@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use crate::domains::analytics::models::{
+    ClickBucket, ClickCount, ClickDimension, ClickEvent, ClickRange, ClickTotals, NewClick,
+    UrlClicks,
+};
+
+#[async_trait]
+pub trait ClickRepository: Send + Sync {
+    /// Inserts the clicks with a single statement. Clicks on links deleted
+    /// since the visit are dropped instead of failing the batch. Human clicks
+    /// are added to their link's click count, unless the redirect already did,
+    /// and to their variant's visit count in the same transaction.
+    async fn create_many(&self, new_clicks: &[NewClick]) -> Result<(), sqlx::Error>;
+
+    /// Today's date in the time zone, `None` when the zone is unknown.
+    async fn local_date(&self, timezone: &String) -> Result<Option<NaiveDate>, sqlx::Error>;
+
+    /// Rolled up clicks are counted from the daily rollups for the UTC days
+    /// the range covers whole and from the 15 minute rollups for the rest,
+    /// clicks not rolled up yet from the raw clicks. The other stats queries
+    /// below read clicks the same way.
+    async fn get_click_totals(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+    ) -> Result<ClickTotals, sqlx::Error>;
+
+    /// Clicks per `day` or `hour`, buckets without clicks included. Daily
+    /// rollups are only read for days that fall in a single bucket, so hourly
+    /// series and daily ones in zones other than UTC use the 15 minute rollups.
+    async fn get_click_series(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+        granularity: &String,
+    ) -> Result<Vec<ClickBucket>, sqlx::Error>;
+
+    /// Clicks per value of the dimension, most clicked first. Clicks without
+    /// a value are counted under `None`, for referrers that is a direct visit.
+    async fn get_top_values(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+        dimension: &ClickDimension,
+        limit: &i64,
+    ) -> Result<Vec<ClickCount>, sqlx::Error>;
+
+    /// Clicks per link, links without clicks in the range are left out.
+    async fn get_clicks_per_url(
+        &self,
+        url_ids: &[Uuid],
+        range: &ClickRange,
+    ) -> Result<Vec<UrlClicks>, sqlx::Error>;
+
+    /// Adds the clicks not rolled up yet to the rollups, in the order they
+    /// were written. Returns the number of clicks added, 0 while another
+    /// instance is rolling up.
+    async fn roll_up(&self) -> Result<u64, sqlx::Error>;
+
+    /// Deletes raw clicks older than `before` that are already rolled up.
+    async fn prune_clicks(&self, before: &NaiveDateTime) -> Result<u64, sqlx::Error>;
+
+    /// Streams the raw clicks on the user's links, or on just `url_id`, oldest
+    /// first. Clicks already pruned are not included. Rows are fetched as the
+    /// receiver reads them, dropping it stops the query.
+    fn stream_clicks(
+        &self,
+        user_id: &Uuid,
+        url_id: Option<Uuid>,
+        range: &ClickRange,
+    ) -> ReceiverStream<Result<ClickEvent, sqlx::Error>>;
+}
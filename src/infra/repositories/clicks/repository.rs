@@ -0,0 +1,577 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::{Pool, Postgres};
+use tokio::sync::mpsc;
+use tokio_stream::{StreamExt, wrappers::ReceiverStream};
+use uuid::Uuid;
+
+use crate::domains::analytics::{
+    classify::ClickKind,
+    models::{
+        ClickBucket, ClickCount, ClickDimension, ClickEvent, ClickRange, ClickTotals, NewClick,
+        UrlClicks,
+    },
+};
+
+use super::interface::ClickRepository;
+
+// rows fetched ahead of a slow reader
+const STREAM_BUFFER: usize = 256;
+// raw clicks added to the rollups per transaction
+const ROLLUP_BATCH: i64 = 50_000;
+// raw clicks deleted per statement when pruning
+const PRUNE_BATCH: i64 = 10_000;
+
+pub struct ClickRepo {
+    db: Pool<Postgres>,
+}
+
+impl ClickRepo {
+    pub fn new(db: Pool<Postgres>) -> Self {
+        ClickRepo { db }
+    }
+}
+
+#[async_trait]
+impl ClickRepository for ClickRepo {
+    async fn create_many(&self, new_clicks: &[NewClick]) -> Result<(), sqlx::Error> {
+        let ids: Vec<Uuid> = new_clicks.iter().map(|_| Uuid::new_v4()).collect();
+        let url_ids: Vec<Uuid> = new_clicks.iter().map(|c| c.url_id).collect();
+        let clicked_at: Vec<NaiveDateTime> = new_clicks.iter().map(|c| c.clicked_at).collect();
+        let referrers: Vec<Option<String>> =
+            new_clicks.iter().map(|c| c.referrer.clone()).collect();
+        let user_agents: Vec<Option<String>> =
+            new_clicks.iter().map(|c| c.user_agent.clone()).collect();
+        let ip_hashes: Vec<Option<String>> = new_clicks.iter().map(|c| c.ip_hash.clone()).collect();
+        let countries: Vec<Option<String>> = new_clicks.iter().map(|c| c.country.clone()).collect();
+        let browsers: Vec<Option<String>> = new_clicks.iter().map(|c| c.browser.clone()).collect();
+        let oses: Vec<Option<String>> = new_clicks.iter().map(|c| c.os.clone()).collect();
+        let devices: Vec<Option<String>> = new_clicks.iter().map(|c| c.device.clone()).collect();
+        let variant_ids: Vec<Option<Uuid>> = new_clicks.iter().map(|c| c.variant_id).collect();
+        let kinds: Vec<String> = new_clicks
+            .iter()
+            .map(|c| c.kind.as_str().to_string())
+            .collect();
+        let human = || new_clicks.iter().filter(|c| c.kind == ClickKind::Human);
+        let uncounted_url_ids: Vec<Uuid> =
+            human().filter(|c| !c.counted).map(|c| c.url_id).collect();
+        let visited_variant_ids: Vec<Uuid> = human().filter_map(|c| c.variant_id).collect();
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO clicks (
+                id, url_id, clicked_at, referrer, user_agent, ip_hash, country, browser, os,
+                device, variant_id, kind
+            )
+            SELECT * FROM UNNEST(
+                $1::UUID[], $2::UUID[], $3::TIMESTAMP[], $4::TEXT[], $5::TEXT[], $6::TEXT[],
+                $7::TEXT[], $8::TEXT[], $9::TEXT[], $10::TEXT[], $11::UUID[], $12::TEXT[]
+            ) AS c (
+                id, url_id, clicked_at, referrer, user_agent, ip_hash, country, browser, os,
+                device, variant_id, kind
+            )
+            WHERE EXISTS (SELECT 1 FROM urls WHERE urls.id = c.url_id)
+            "#,
+            &ids,
+            &url_ids,
+            &clicked_at,
+            &referrers as &[Option<String>],
+            &user_agents as &[Option<String>],
+            &ip_hashes as &[Option<String>],
+            &countries as &[Option<String>],
+            &browsers as &[Option<String>],
+            &oses as &[Option<String>],
+            &devices as &[Option<String>],
+            &variant_ids as &[Option<Uuid>],
+            &kinds,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if !uncounted_url_ids.is_empty() {
+            sqlx::query!(
+                r#"
+                UPDATE urls u
+                SET click_count = u.click_count + c.clicks::INT
+                FROM (
+                    SELECT id, COUNT(*) AS clicks FROM UNNEST($1::UUID[]) AS id GROUP BY id
+                ) c
+                WHERE u.id = c.id
+                "#,
+                &uncounted_url_ids
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        if !visited_variant_ids.is_empty() {
+            sqlx::query!(
+                r#"
+                UPDATE url_variants v
+                SET visit_count = v.visit_count + c.visits
+                FROM (
+                    SELECT id, COUNT(*) AS visits FROM UNNEST($1::UUID[]) AS id GROUP BY id
+                ) c
+                WHERE v.id = c.id
+                "#,
+                &visited_variant_ids
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn local_date(&self, timezone: &String) -> Result<Option<NaiveDate>, sqlx::Error> {
+        let today = sqlx::query_scalar!(
+            r#"
+            SELECT (NOW() AT TIME ZONE name)::DATE AS "today!"
+            FROM pg_timezone_names
+            WHERE name = $1
+            "#,
+            timezone
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(today)
+    }
+
+    async fn get_click_totals(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+    ) -> Result<ClickTotals, sqlx::Error> {
+        let totals = sqlx::query_as!(
+            ClickTotals,
+            r#"
+            WITH span AS (
+                SELECT ($2::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS start,
+                    ($3::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS finish
+            ),
+            days AS (
+                SELECT d.day
+                FROM span s, generate_series(
+                    date_trunc('day', s.start - INTERVAL '1 microsecond') + INTERVAL '1 day',
+                    date_trunc('day', s.finish) - INTERVAL '1 day',
+                    INTERVAL '1 day'
+                ) AS d(day)
+            ),
+            hits AS (
+                SELECT CASE WHEN r.dimension = 'total' THEN r.clicks ELSE 0 END AS clicks,
+                    CASE WHEN r.dimension = 'visitor' THEN NULLIF(r.value, '') END AS visitor
+                FROM click_rollups_daily r
+                WHERE r.url_id = $1
+                    AND r.dimension IN ('total', 'visitor')
+                    AND r.day IN (SELECT day FROM days)
+                    AND ($5 OR r.kind = 'human')
+                UNION ALL
+                SELECT CASE WHEN r.dimension = 'total' THEN r.clicks ELSE 0 END,
+                    CASE WHEN r.dimension = 'visitor' THEN NULLIF(r.value, '') END
+                FROM click_rollups r, span s
+                WHERE r.url_id = $1
+                    AND r.dimension IN ('total', 'visitor')
+                    AND r.bucket >= s.start
+                    AND r.bucket < s.finish
+                    AND date_trunc('day', r.bucket) NOT IN (SELECT day FROM days)
+                    AND ($5 OR r.kind = 'human')
+                UNION ALL
+                SELECT 1, c.ip_hash
+                FROM clicks c, span s
+                WHERE c.url_id = $1
+                    AND NOT c.rolled_up
+                    AND c.clicked_at >= s.start
+                    AND c.clicked_at < s.finish
+                    AND ($5 OR c.kind = 'human')
+            )
+            SELECT COALESCE(SUM(clicks), 0)::BIGINT AS "clicks!",
+                COUNT(DISTINCT visitor) AS "unique_visitors!"
+            FROM hits
+            "#,
+            url_id,
+            range.from,
+            range.to,
+            range.timezone,
+            range.include_bots
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(totals)
+    }
+
+    async fn get_click_series(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+        granularity: &String,
+    ) -> Result<Vec<ClickBucket>, sqlx::Error> {
+        let series = sqlx::query_as!(
+            ClickBucket,
+            r#"
+            WITH span AS (
+                SELECT ($2::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS start,
+                    ($3::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS finish
+            ),
+            days AS (
+                SELECT d.day
+                FROM span s, generate_series(
+                    date_trunc('day', s.start - INTERVAL '1 microsecond') + INTERVAL '1 day',
+                    date_trunc('day', s.finish) - INTERVAL '1 day',
+                    INTERVAL '1 day'
+                ) AS d(day)
+                WHERE date_trunc($5, d.day AT TIME ZONE 'UTC' AT TIME ZONE $4) = date_trunc(
+                    $5,
+                    (d.day + INTERVAL '1 day' - INTERVAL '1 microsecond') AT TIME ZONE 'UTC'
+                        AT TIME ZONE $4
+                )
+            ),
+            hits AS (
+                SELECT r.day AS at,
+                    CASE WHEN r.dimension = 'total' THEN r.clicks ELSE 0 END AS clicks,
+                    CASE WHEN r.dimension = 'visitor' THEN NULLIF(r.value, '') END AS visitor
+                FROM click_rollups_daily r
+                WHERE r.url_id = $1
+                    AND r.dimension IN ('total', 'visitor')
+                    AND r.day IN (SELECT day FROM days)
+                    AND ($6 OR r.kind = 'human')
+                UNION ALL
+                SELECT r.bucket,
+                    CASE WHEN r.dimension = 'total' THEN r.clicks ELSE 0 END,
+                    CASE WHEN r.dimension = 'visitor' THEN NULLIF(r.value, '') END
+                FROM click_rollups r, span s
+                WHERE r.url_id = $1
+                    AND r.dimension IN ('total', 'visitor')
+                    AND r.bucket >= s.start
+                    AND r.bucket < s.finish
+                    AND date_trunc('day', r.bucket) NOT IN (SELECT day FROM days)
+                    AND ($6 OR r.kind = 'human')
+                UNION ALL
+                SELECT c.clicked_at, 1, c.ip_hash
+                FROM clicks c, span s
+                WHERE c.url_id = $1
+                    AND NOT c.rolled_up
+                    AND c.clicked_at >= s.start
+                    AND c.clicked_at < s.finish
+                    AND ($6 OR c.kind = 'human')
+            ),
+            buckets AS (
+                SELECT date_trunc($5, at AT TIME ZONE 'UTC' AT TIME ZONE $4) AS bucket, clicks,
+                    visitor
+                FROM hits
+            )
+            SELECT b.bucket AS "bucket!", COALESCE(SUM(h.clicks), 0)::BIGINT AS "clicks!",
+                COUNT(DISTINCT h.visitor) AS "unique_visitors!"
+            FROM generate_series(
+                $2::TIMESTAMP,
+                $3::TIMESTAMP - ('1 ' || $5)::INTERVAL,
+                ('1 ' || $5)::INTERVAL
+            ) AS b(bucket)
+            LEFT JOIN buckets h ON h.bucket = b.bucket
+            GROUP BY b.bucket
+            ORDER BY b.bucket
+            "#,
+            url_id,
+            range.from,
+            range.to,
+            range.timezone,
+            granularity,
+            range.include_bots
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(series)
+    }
+
+    async fn get_top_values(
+        &self,
+        url_id: &Uuid,
+        range: &ClickRange,
+        dimension: &ClickDimension,
+        limit: &i64,
+    ) -> Result<Vec<ClickCount>, sqlx::Error> {
+        let counts = sqlx::query_as!(
+            ClickCount,
+            r#"
+            WITH span AS (
+                SELECT ($2::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS start,
+                    ($3::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS finish
+            ),
+            days AS (
+                SELECT d.day
+                FROM span s, generate_series(
+                    date_trunc('day', s.start - INTERVAL '1 microsecond') + INTERVAL '1 day',
+                    date_trunc('day', s.finish) - INTERVAL '1 day',
+                    INTERVAL '1 day'
+                ) AS d(day)
+            ),
+            hits AS (
+                SELECT NULLIF(r.value, '') AS value, r.clicks
+                FROM click_rollups_daily r
+                WHERE r.url_id = $1
+                    AND r.dimension = $5
+                    AND r.day IN (SELECT day FROM days)
+                    AND ($7 OR r.kind = 'human')
+                UNION ALL
+                SELECT NULLIF(r.value, ''), r.clicks
+                FROM click_rollups r, span s
+                WHERE r.url_id = $1
+                    AND r.dimension = $5
+                    AND r.bucket >= s.start
+                    AND r.bucket < s.finish
+                    AND date_trunc('day', r.bucket) NOT IN (SELECT day FROM days)
+                    AND ($7 OR r.kind = 'human')
+                UNION ALL
+                SELECT
+                    CASE $5
+                        WHEN 'referrer' THEN lower(
+                            substring(c.referrer FROM '^[a-zA-Z][a-zA-Z0-9+.-]*://([^/?#:]+)')
+                        )
+                        WHEN 'country' THEN c.country
+                        WHEN 'browser' THEN c.browser
+                        WHEN 'os' THEN c.os
+                        WHEN 'device' THEN c.device
+                    END,
+                    1
+                FROM clicks c, span s
+                WHERE c.url_id = $1
+                    AND NOT c.rolled_up
+                    AND c.clicked_at >= s.start
+                    AND c.clicked_at < s.finish
+                    AND ($7 OR c.kind = 'human')
+            )
+            SELECT value, SUM(clicks)::BIGINT AS "clicks!"
+            FROM hits
+            GROUP BY 1
+            ORDER BY 2 DESC, 1
+            LIMIT $6
+            "#,
+            url_id,
+            range.from,
+            range.to,
+            range.timezone,
+            dimension.as_str(),
+            limit,
+            range.include_bots
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(counts)
+    }
+
+    async fn get_clicks_per_url(
+        &self,
+        url_ids: &[Uuid],
+        range: &ClickRange,
+    ) -> Result<Vec<UrlClicks>, sqlx::Error> {
+        let clicks = sqlx::query_as!(
+            UrlClicks,
+            r#"
+            WITH span AS (
+                SELECT ($2::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS start,
+                    ($3::TIMESTAMP AT TIME ZONE $4) AT TIME ZONE 'UTC' AS finish
+            ),
+            days AS (
+                SELECT d.day
+                FROM span s, generate_series(
+                    date_trunc('day', s.start - INTERVAL '1 microsecond') + INTERVAL '1 day',
+                    date_trunc('day', s.finish) - INTERVAL '1 day',
+                    INTERVAL '1 day'
+                ) AS d(day)
+            ),
+            hits AS (
+                SELECT r.url_id, r.clicks
+                FROM click_rollups_daily r
+                WHERE r.url_id = ANY($1)
+                    AND r.dimension = 'total'
+                    AND r.day IN (SELECT day FROM days)
+                    AND ($5 OR r.kind = 'human')
+                UNION ALL
+                SELECT r.url_id, r.clicks
+                FROM click_rollups r, span s
+                WHERE r.url_id = ANY($1)
+                    AND r.dimension = 'total'
+                    AND r.bucket >= s.start
+                    AND r.bucket < s.finish
+                    AND date_trunc('day', r.bucket) NOT IN (SELECT day FROM days)
+                    AND ($5 OR r.kind = 'human')
+                UNION ALL
+                SELECT c.url_id, 1
+                FROM clicks c, span s
+                WHERE c.url_id = ANY($1)
+                    AND NOT c.rolled_up
+                    AND c.clicked_at >= s.start
+                    AND c.clicked_at < s.finish
+                    AND ($5 OR c.kind = 'human')
+            )
+            SELECT url_id AS "url_id!", SUM(clicks)::BIGINT AS "clicks!"
+            FROM hits
+            GROUP BY url_id
+            "#,
+            url_ids,
+            range.from,
+            range.to,
+            range.timezone,
+            range.include_bots
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(clicks)
+    }
+
+    async fn roll_up(&self) -> Result<u64, sqlx::Error> {
+        let mut rolled_up = 0;
+        loop {
+            let mut tx = self.db.begin().await?;
+
+            // one instance rolls up at a time, concurrent upserts of the same
+            // rollup rows could deadlock
+            let locked = sqlx::query_scalar!(
+                r#"SELECT pg_try_advisory_xact_lock(hashtext('click_rollups')) AS "locked!""#
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if !locked {
+                return Ok(rolled_up);
+            }
+
+            // marking the clicks and counting them in one statement adds every
+            // click exactly once, however late it was written
+            let clicks = sqlx::query_scalar!(
+                r#"
+                WITH batch AS (
+                    UPDATE clicks SET rolled_up = TRUE
+                    WHERE id IN (
+                        SELECT id FROM clicks
+                        WHERE NOT rolled_up
+                        ORDER BY clicked_at
+                        LIMIT $1
+                    )
+                    RETURNING url_id, clicked_at, kind, ip_hash, referrer, country, browser, os,
+                        device
+                ),
+                hits AS (
+                    SELECT b.url_id, b.clicked_at, b.kind, d.dimension,
+                        COALESCE(d.value, '') AS value
+                    FROM batch b
+                    CROSS JOIN LATERAL (
+                        VALUES
+                            ('total', NULL::TEXT),
+                            ('visitor', b.ip_hash),
+                            (
+                                'referrer',
+                                lower(
+                                    substring(b.referrer FROM '^[a-zA-Z][a-zA-Z0-9+.-]*://([^/?#:]+)')
+                                )
+                            ),
+                            ('country', b.country),
+                            ('browser', b.browser),
+                            ('os', b.os),
+                            ('device', b.device)
+                    ) AS d(dimension, value)
+                ),
+                buckets AS (
+                    INSERT INTO click_rollups (url_id, bucket, kind, dimension, value, clicks)
+                    SELECT url_id, date_bin('15 minutes', clicked_at, TIMESTAMP 'epoch'), kind,
+                        dimension, value, COUNT(*)
+                    FROM hits
+                    GROUP BY 1, 2, 3, 4, 5
+                    ON CONFLICT (url_id, bucket, kind, dimension, value)
+                        DO UPDATE SET clicks = click_rollups.clicks + EXCLUDED.clicks
+                ),
+                days AS (
+                    INSERT INTO click_rollups_daily (url_id, day, kind, dimension, value, clicks)
+                    SELECT url_id, date_trunc('day', clicked_at), kind, dimension, value, COUNT(*)
+                    FROM hits
+                    GROUP BY 1, 2, 3, 4, 5
+                    ON CONFLICT (url_id, day, kind, dimension, value)
+                        DO UPDATE SET clicks = click_rollups_daily.clicks + EXCLUDED.clicks
+                )
+                SELECT COUNT(*) AS "clicks!" FROM batch
+                "#,
+                ROLLUP_BATCH
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            rolled_up += clicks as u64;
+            if clicks < ROLLUP_BATCH {
+                return Ok(rolled_up);
+            }
+        }
+    }
+
+    async fn prune_clicks(&self, before: &NaiveDateTime) -> Result<u64, sqlx::Error> {
+        let mut pruned = 0;
+        loop {
+            // small batches keep each delete from holding locks for long
+            let deleted = sqlx::query!(
+                r#"
+                DELETE FROM clicks
+                WHERE id IN (
+                    SELECT id FROM clicks
+                    WHERE rolled_up AND clicked_at < $1
+                    LIMIT $2
+                )
+                "#,
+                before,
+                PRUNE_BATCH
+            )
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+            pruned += deleted;
+            if deleted < PRUNE_BATCH as u64 {
+                return Ok(pruned);
+            }
+        }
+    }
+
+    fn stream_clicks(
+        &self,
+        user_id: &Uuid,
+        url_id: Option<Uuid>,
+        range: &ClickRange,
+    ) -> ReceiverStream<Result<ClickEvent, sqlx::Error>> {
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let db = self.db.clone();
+        let user_id = *user_id;
+        let range = range.clone();
+
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as!(
+                ClickEvent,
+                r#"
+                SELECT c.clicked_at AT TIME ZONE 'UTC' AS "clicked_at!", c.url_id, u.short_url AS short_code, c.kind, c.referrer,
+                    c.user_agent, c.browser, c.os, c.device, c.country, c.ip_hash AS visitor,
+                    c.variant_id
+                FROM clicks c
+                JOIN urls u ON u.id = c.url_id
+                WHERE u.user_id = $1
+                    AND ($2::UUID IS NULL OR c.url_id = $2)
+                    AND c.clicked_at >= ($3::TIMESTAMP AT TIME ZONE $5) AT TIME ZONE 'UTC'
+                    AND c.clicked_at < ($4::TIMESTAMP AT TIME ZONE $5) AT TIME ZONE 'UTC'
+                    AND ($6 OR c.kind = 'human')
+                ORDER BY c.clicked_at
+                "#,
+                user_id,
+                url_id,
+                range.from,
+                range.to,
+                range.timezone,
+                range.include_bots
+            )
+            .fetch(&db);
+
+            while let Some(row) = rows.next().await {
+                let failed = row.is_err();
+                if sender.send(row).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        ReceiverStream::new(receiver)
+    }
+}
@@ -1,2 +1,3 @@
+pub mod clicks;
 pub mod urls;
 pub mod users;
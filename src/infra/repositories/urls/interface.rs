@@ -1,24 +1,94 @@
 use sqlx::{Pool, Postgres, Transaction};
-use uuid::Uuid;
 
 use async_trait::async_trait;
+use uuid::Uuid;
 
-use crate::domains::urls::models::Url;
+use crate::domains::urls::models::{
+    NewUrl, RuleConditions, Url, UrlRule, UrlVariant, UtmParams, UtmPreset,
+};
 
 #[async_trait]
 pub trait UrlRepository: Send + Sync {
-    async fn create(
-        &self,
-        url_req: &String,
-        short_url: &String,
-        user_id: &Uuid,
-    ) -> Result<Url, sqlx::Error>;
+    async fn create(&self, new_url: &NewUrl) -> Result<Url, sqlx::Error>;
 
     async fn delete(&self, id: &String) -> Result<Url, sqlx::Error>;
 
-    async fn get_url_by_short_url(&self, short_url: &String) -> Result<Url, sqlx::Error>;
+    /// The live link for the code or, when there is none, the most recently
+    /// deleted one.
+    async fn get_url_by_short_url(&self, short_url: &String) -> Result<Option<Url>, sqlx::Error>;
+
+    async fn get_url_by_id(&self, id: &String) -> Result<Url, sqlx::Error>;
+
+    /// Bumps the visit counter unless the link has used up its clicks.
+    /// Returns `None` when the limit was already reached.
+    async fn register_visit(&self, id: &Uuid) -> Result<Option<Url>, sqlx::Error>;
+
+    async fn short_url_exists(&self, short_url: &String) -> Result<bool, sqlx::Error>;
+
+    async fn next_code_sequence(&self) -> Result<i64, sqlx::Error>;
 
     async fn favourite_url(&self, id: &String, state: &bool) -> Result<Url, sqlx::Error>;
 
+    async fn set_url_warning(
+        &self,
+        id: &String,
+        warn: &bool,
+        reason: &Option<String>,
+    ) -> Result<Url, sqlx::Error>;
+
     async fn get_user_urls(&self, user_id: &String) -> Result<Vec<Url>, sqlx::Error>;
+
+    async fn create_utm_preset(
+        &self,
+        user_id: &Uuid,
+        name: &String,
+        params: &UtmParams,
+    ) -> Result<UtmPreset, sqlx::Error>;
+
+    async fn get_utm_preset(&self, id: &Uuid) -> Result<UtmPreset, sqlx::Error>;
+
+    async fn get_user_utm_presets(&self, user_id: &String) -> Result<Vec<UtmPreset>, sqlx::Error>;
+
+    /// Deletes the preset and detaches it from any links using it.
+    async fn delete_utm_preset(
+        &self,
+        id: &String,
+        user_id: &String,
+    ) -> Result<UtmPreset, sqlx::Error>;
+
+    async fn get_url_rules(&self, url_id: &Uuid) -> Result<Vec<UrlRule>, sqlx::Error>;
+
+    /// Appends a rule after the link's current last rule.
+    async fn create_url_rule(
+        &self,
+        url_id: &Uuid,
+        conditions: &RuleConditions,
+        target_url: &String,
+    ) -> Result<UrlRule, sqlx::Error>;
+
+    /// Replaces every rule of the link, keeping the given order.
+    async fn replace_url_rules(
+        &self,
+        url_id: &Uuid,
+        rules: &[(RuleConditions, String)],
+    ) -> Result<Vec<UrlRule>, sqlx::Error>;
+
+    async fn delete_url_rule(
+        &self,
+        url_id: &Uuid,
+        rule_id: &String,
+    ) -> Result<UrlRule, sqlx::Error>;
+
+    async fn get_url_variants(&self, url_id: &Uuid) -> Result<Vec<UrlVariant>, sqlx::Error>;
+
+    /// Replaces the variants of the link and sets whether visitors stick to
+    /// the variant they were first sent to. Variants given with an id are
+    /// updated in place and keep their visit count, variants left out are
+    /// deleted. Fails with `RowNotFound` when an id isn't one of the link's.
+    async fn replace_url_variants(
+        &self,
+        url_id: &Uuid,
+        variants: &[(Option<Uuid>, String, i32)],
+        sticky: bool,
+    ) -> Result<Vec<UrlVariant>, sqlx::Error>;
 }
@@ -1,8 +1,10 @@
 use async_trait::async_trait;
-use sqlx::{Pool, Postgres, Transaction};
+use sqlx::{Pool, Postgres, Transaction, types::Json};
 use uuid::Uuid;
 
-use crate::domains::urls::models::Url;
+use crate::domains::urls::models::{
+    NewUrl, RuleConditions, Url, UrlRule, UrlVariant, UtmParams, UtmPreset,
+};
 
 use super::interface::UrlRepository;
 
@@ -21,23 +23,42 @@ impl UrlRepository for UrlRepo {
     async fn create(
         &self,
         //tx: &mut Transaction<'_, Postgres>,
-        url_req: &String,
-        short_url: &String,
-        user_id: &Uuid,
+        new_url: &NewUrl,
     ) -> Result<Url, sqlx::Error> {
         let mut tx = self.db.begin().await?;
         let id = Uuid::new_v4();
         let url = sqlx::query_as!(
             Url,
             r#"
-            INSERT INTO urls (id, user_id, url, short_url, deleted, created_at)
-            VALUES ($1, $2, $3, $4, false, NOW())
-            RETURNING id, user_id, url, short_url, favourite, deleted, created_at
+            INSERT INTO urls (
+                id, user_id, url, short_url, is_alias, redirect_type, active_from, expires_at,
+                max_clicks, password_hash, forward_query, forward_path, utm_params, utm_preset_id,
+                deep_link, allow_indexing, deleted, created_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, false, NOW()
+            )
+            RETURNING id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
             "#,
             id,
-            user_id,
-            url_req,
-            short_url,
+            new_url.user_id,
+            new_url.url,
+            new_url.short_url,
+            new_url.is_alias,
+            new_url.redirect_type,
+            new_url.active_from,
+            new_url.expires_at,
+            new_url.max_clicks,
+            new_url.password_hash,
+            new_url.forward_query,
+            new_url.forward_path,
+            new_url.utm_params.clone() as _,
+            new_url.utm_preset_id,
+            new_url.deep_link,
+            new_url.allow_indexing,
         )
         .fetch_one(&mut *tx)
         .await?;
@@ -57,7 +78,10 @@ impl UrlRepository for UrlRepo {
             UPDATE urls
             SET deleted = true
             WHERE id = $1
-            RETURNING id, user_id, url, short_url, favourite, deleted, created_at
+            RETURNING id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
             "#,
             uuid_id,
         )
@@ -68,22 +92,92 @@ impl UrlRepository for UrlRepo {
         Ok(url)
     }
 
-    async fn get_url_by_short_url(&self, short_url: &String) -> Result<Url, sqlx::Error> {
+    async fn get_url_by_short_url(&self, short_url: &String) -> Result<Option<Url>, sqlx::Error> {
         let url = sqlx::query_as!(
             Url,
             r#"
-            SELECT id, user_id, url, short_url, favourite, deleted, created_at
+            SELECT id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
             FROM urls
-            WHERE short_url = $1 AND deleted = false
+            WHERE short_url = $1
+            ORDER BY deleted, created_at DESC
+            LIMIT 1
             "#,
             short_url
         )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(url)
+    }
+
+    async fn get_url_by_id(&self, id: &String) -> Result<Url, sqlx::Error> {
+        let uuid_id =
+            Uuid::parse_str(id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            SELECT id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
+            FROM urls
+            WHERE id = $1 AND deleted = false
+            "#,
+            uuid_id
+        )
         .fetch_one(&self.db)
         .await?;
 
         Ok(url)
     }
 
+    async fn register_visit(&self, id: &Uuid) -> Result<Option<Url>, sqlx::Error> {
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            UPDATE urls
+            SET click_count = click_count + 1
+            WHERE id = $1 AND (max_clicks IS NULL OR click_count < max_clicks)
+            RETURNING id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
+            "#,
+            id,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(url)
+    }
+
+    async fn short_url_exists(&self, short_url: &String) -> Result<bool, sqlx::Error> {
+        let exists = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM urls WHERE short_url = $1 AND deleted = false
+            ) AS "exists!"
+            "#,
+            short_url
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(exists)
+    }
+
+    async fn next_code_sequence(&self) -> Result<i64, sqlx::Error> {
+        let n = sqlx::query_scalar!(r#"SELECT nextval('short_code_seq') AS "n!""#)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(n)
+    }
+
     async fn favourite_url(&self, id: &String, state: &bool) -> Result<Url, sqlx::Error> {
         let mut tx = self.db.begin().await?;
         let uuid_id =
@@ -95,7 +189,10 @@ impl UrlRepository for UrlRepo {
             UPDATE urls
             SET favourite = $2
             WHERE id = $1
-            RETURNING id, user_id, url, short_url, favourite, deleted, created_at
+            RETURNING id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
             "#,
             uuid_id,
             state,
@@ -107,6 +204,36 @@ impl UrlRepository for UrlRepo {
         Ok(url)
     }
 
+    async fn set_url_warning(
+        &self,
+        id: &String,
+        warn: &bool,
+        reason: &Option<String>,
+    ) -> Result<Url, sqlx::Error> {
+        let uuid_id =
+            Uuid::parse_str(id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+
+        let url = sqlx::query_as!(
+            Url,
+            r#"
+            UPDATE urls
+            SET warn = $2, warn_reason = $3
+            WHERE id = $1 AND deleted = false
+            RETURNING id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
+            "#,
+            uuid_id,
+            warn,
+            reason.as_ref(),
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(url)
+    }
+
     async fn get_user_urls(&self, user_id: &String) -> Result<Vec<Url>, sqlx::Error> {
         let uuid_id =
             Uuid::parse_str(user_id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
@@ -114,7 +241,10 @@ impl UrlRepository for UrlRepo {
         let urls = sqlx::query_as!(
             Url,
             r#"
-            SELECT id, user_id, url, short_url, favourite, deleted, created_at
+            SELECT id, user_id, url, short_url, is_alias, redirect_type, active_from,
+                expires_at, max_clicks, click_count, password_hash, forward_query, forward_path,
+                utm_params AS "utm_params: Json<UtmParams>", utm_preset_id, sticky_variants,
+                warn, warn_reason, deep_link, allow_indexing, favourite, deleted, created_at
             FROM urls
             WHERE user_id = $1 AND deleted = false
             "#,
@@ -125,4 +255,288 @@ impl UrlRepository for UrlRepo {
 
         Ok(urls)
     }
+
+    async fn create_utm_preset(
+        &self,
+        user_id: &Uuid,
+        name: &String,
+        params: &UtmParams,
+    ) -> Result<UtmPreset, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        let id = Uuid::new_v4();
+        let preset = sqlx::query_as!(
+            UtmPreset,
+            r#"
+            INSERT INTO utm_presets (id, user_id, name, params, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            RETURNING id, user_id, name, params AS "params: Json<UtmParams>", created_at
+            "#,
+            id,
+            user_id,
+            name,
+            Json(params) as _,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(preset)
+    }
+
+    async fn get_utm_preset(&self, id: &Uuid) -> Result<UtmPreset, sqlx::Error> {
+        let preset = sqlx::query_as!(
+            UtmPreset,
+            r#"
+            SELECT id, user_id, name, params AS "params: Json<UtmParams>", created_at
+            FROM utm_presets
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(preset)
+    }
+
+    async fn get_user_utm_presets(&self, user_id: &String) -> Result<Vec<UtmPreset>, sqlx::Error> {
+        let uuid_id =
+            Uuid::parse_str(user_id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+
+        let presets = sqlx::query_as!(
+            UtmPreset,
+            r#"
+            SELECT id, user_id, name, params AS "params: Json<UtmParams>", created_at
+            FROM utm_presets
+            WHERE user_id = $1
+            ORDER BY name
+            "#,
+            uuid_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(presets)
+    }
+
+    async fn delete_utm_preset(
+        &self,
+        id: &String,
+        user_id: &String,
+    ) -> Result<UtmPreset, sqlx::Error> {
+        let uuid_id =
+            Uuid::parse_str(id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+        let uuid_user_id =
+            Uuid::parse_str(user_id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+
+        let preset = sqlx::query_as!(
+            UtmPreset,
+            r#"
+            DELETE FROM utm_presets
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, user_id, name, params AS "params: Json<UtmParams>", created_at
+            "#,
+            uuid_id,
+            uuid_user_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(preset)
+    }
+
+    async fn get_url_rules(&self, url_id: &Uuid) -> Result<Vec<UrlRule>, sqlx::Error> {
+        let rules = sqlx::query_as!(
+            UrlRule,
+            r#"
+            SELECT id, url_id, position, conditions AS "conditions: Json<RuleConditions>", target_url, created_at
+            FROM url_rules
+            WHERE url_id = $1
+            ORDER BY position
+            "#,
+            url_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rules)
+    }
+
+    async fn create_url_rule(
+        &self,
+        url_id: &Uuid,
+        conditions: &RuleConditions,
+        target_url: &String,
+    ) -> Result<UrlRule, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        let id = Uuid::new_v4();
+        let rule = sqlx::query_as!(
+            UrlRule,
+            r#"
+            INSERT INTO url_rules (id, url_id, position, conditions, target_url, created_at)
+            VALUES (
+                $1, $2,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM url_rules WHERE url_id = $2),
+                $3, $4, NOW()
+            )
+            RETURNING id, url_id, position, conditions AS "conditions: Json<RuleConditions>", target_url, created_at
+            "#,
+            id,
+            url_id,
+            Json(conditions) as _,
+            target_url,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(rule)
+    }
+
+    async fn replace_url_rules(
+        &self,
+        url_id: &Uuid,
+        rules: &[(RuleConditions, String)],
+    ) -> Result<Vec<UrlRule>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query!("DELETE FROM url_rules WHERE url_id = $1", url_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut created = Vec::new();
+        for (position, (conditions, target_url)) in rules.iter().enumerate() {
+            let id = Uuid::new_v4();
+            let rule = sqlx::query_as!(
+                UrlRule,
+                r#"
+                INSERT INTO url_rules (id, url_id, position, conditions, target_url, created_at)
+                VALUES ($1, $2, $3, $4, $5, NOW())
+                RETURNING id, url_id, position, conditions AS "conditions: Json<RuleConditions>", target_url, created_at
+                "#,
+                id,
+                url_id,
+                position as i32,
+                Json(conditions) as _,
+                target_url,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            created.push(rule);
+        }
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    async fn delete_url_rule(
+        &self,
+        url_id: &Uuid,
+        rule_id: &String,
+    ) -> Result<UrlRule, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        let uuid_id =
+            Uuid::parse_str(rule_id).map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+
+        let rule = sqlx::query_as!(
+            UrlRule,
+            r#"
+            DELETE FROM url_rules
+            WHERE id = $1 AND url_id = $2
+            RETURNING id, url_id, position, conditions AS "conditions: Json<RuleConditions>", target_url, created_at
+            "#,
+            uuid_id,
+            url_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(rule)
+    }
+
+    async fn get_url_variants(&self, url_id: &Uuid) -> Result<Vec<UrlVariant>, sqlx::Error> {
+        let variants = sqlx::query_as!(
+            UrlVariant,
+            r#"
+            SELECT id, url_id, position, target_url, weight, visit_count, created_at
+            FROM url_variants
+            WHERE url_id = $1
+            ORDER BY position
+            "#,
+            url_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(variants)
+    }
+
+    async fn replace_url_variants(
+        &self,
+        url_id: &Uuid,
+        variants: &[(Option<Uuid>, String, i32)],
+        sticky: bool,
+    ) -> Result<Vec<UrlVariant>, sqlx::Error> {
+        let kept: Vec<Uuid> = variants.iter().filter_map(|(id, _, _)| *id).collect();
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            "DELETE FROM url_variants WHERE url_id = $1 AND NOT (id = ANY($2))",
+            url_id,
+            &kept
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE urls SET sticky_variants = $2 WHERE id = $1",
+            url_id,
+            sticky
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mut created = Vec::new();
+        for (position, (id, target_url, weight)) in variants.iter().enumerate() {
+            let variant = match id {
+                Some(id) => {
+                    sqlx::query_as!(
+                        UrlVariant,
+                        r#"
+                    UPDATE url_variants SET position = $3, target_url = $4, weight = $5
+                    WHERE id = $1 AND url_id = $2
+                    RETURNING id, url_id, position, target_url, weight, visit_count, created_at
+                    "#,
+                        id,
+                        url_id,
+                        position as i32,
+                        target_url,
+                        weight,
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?
+                }
+                None => {
+                    sqlx::query_as!(
+                        UrlVariant,
+                        r#"
+                    INSERT INTO url_variants (id, url_id, position, target_url, weight, created_at)
+                    VALUES ($1, $2, $3, $4, $5, NOW())
+                    RETURNING id, url_id, position, target_url, weight, visit_count, created_at
+                    "#,
+                        Uuid::new_v4(),
+                        url_id,
+                        position as i32,
+                        target_url,
+                        weight,
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?
+                }
+            };
+            created.push(variant);
+        }
+        tx.commit().await?;
+
+        Ok(created)
+    }
 }
@@ -11,4 +11,10 @@ pub trait UserRepository: Send + Sync {
     async fn get_user_by_username(&self, username: &String) -> Result<User, sqlx::Error>;
 
     async fn get_user_by_id(&self, id: &String) -> Result<User, sqlx::Error>;
+
+    async fn update_dead_link_url(
+        &self,
+        user_id: &String,
+        dead_link_url: &Option<String>,
+    ) -> Result<User, sqlx::Error>;
 }
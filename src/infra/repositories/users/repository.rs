@@ -25,7 +25,7 @@ impl UserRepository for UsersRepo {
             r#"
             INSERT INTO users (id, username, password_hash, deleted, created_at)
             VALUES ($1, $2, $3, false, NOW())
-            RETURNING id, username, password_hash, deleted, created_at
+            RETURNING id, username, password_hash, dead_link_url, deleted, created_at
             "#,
             id,
             username,
@@ -42,7 +42,7 @@ impl UserRepository for UsersRepo {
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, username, password_hash, deleted, created_at
+            SELECT id, username, password_hash, dead_link_url, deleted, created_at
             FROM users
             WHERE username = $1 AND password_hash = $2 AND deleted = false
             "#,
@@ -59,7 +59,7 @@ impl UserRepository for UsersRepo {
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, username, password_hash, deleted, created_at
+            SELECT id, username, password_hash, dead_link_url, deleted, created_at
             FROM users
             WHERE username = $1 AND deleted = false
             "#,
@@ -77,7 +77,7 @@ impl UserRepository for UsersRepo {
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, username, password_hash, deleted, created_at
+            SELECT id, username, password_hash, dead_link_url, deleted, created_at
             FROM users
             WHERE id = $1 AND deleted = false
             "#,
@@ -88,4 +88,28 @@ impl UserRepository for UsersRepo {
 
         Ok(user)
     }
+
+    async fn update_dead_link_url(
+        &self,
+        user_id: &String,
+        dead_link_url: &Option<String>,
+    ) -> Result<User, sqlx::Error> {
+        let uuid_id = uuid::Uuid::parse_str(user_id)
+            .map_err(|_| sqlx::Error::Decode("Invalid UUID".into()))?;
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET dead_link_url = $2
+            WHERE id = $1 AND deleted = false
+            RETURNING id, username, password_hash, dead_link_url, deleted, created_at
+            "#,
+            uuid_id,
+            dead_link_url.as_ref()
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(user)
+    }
 }
@@ -1,8 +1,10 @@
 use app::create_router;
 use axum::{Router, routing::get, routing::post};
 use config::config::Config;
-use infra::db::setup_database;
-use std::net::SocketAddr;
+use infra::{
+    click_rollups::ClickRollups, db::setup_database, repositories::clicks::repository::ClickRepo,
+};
+use std::{net::SocketAddr, sync::Arc};
 use tracing::info;
 
 mod app;
@@ -16,10 +18,31 @@ mod middleware;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
     let config = Config::from_env()?;
     let pool = setup_database(&config).await?;
 
+    // `url-shortener rollup` rolls up and prunes clicks once, e.g. from cron
+    if let Some(command) = std::env::args().nth(1) {
+        return match command.as_str() {
+            "rollup" => {
+                let click_repo = Arc::new(ClickRepo::new(pool));
+                let report = ClickRollups::new(click_repo, config.click_retention_days)
+                    .run()
+                    .await?;
+                info!(
+                    "Rolled up {} clicks, pruned {} raw clicks",
+                    report.rolled_up, report.pruned
+                );
+                Ok(())
+            }
+            _ => Err(format!("Unknown command {}", command).into()),
+        };
+    }
+
     let state = app_state::AppState::new(&config, pool);
+    let clicks = state.clicks.clone();
 
     let app = create_router(&config, state);
 
@@ -28,9 +51,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(app::shutdown_signal())
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(app::shutdown_signal())
+    .await?;
+
+    // write out the clicks still buffered before exiting
+    clicks.shutdown().await;
 
     info!("Server stopped");
 